@@ -0,0 +1,29 @@
+//! compiles the bundled libspeex decoder for mdx-server's `.spx` to wav conversion, only with the
+//! `server` feature
+
+fn main() {
+    println!("cargo:rerun-if-changed=speex");
+    #[cfg(feature = "server")]
+    build_speex();
+}
+
+#[cfg(feature = "server")]
+fn build_speex() {
+    // libspeex 1.2.1 in floating point, the encoder is compiled too because the mode tables
+    // reference it. kiss_fft, smallft and vorbis_psy are only used by the encoder's psychoacoustic
+    // model, which is off
+    let sources = [
+        "bits.c", "cb_search.c", "exc_10_16_table.c", "exc_10_32_table.c", "exc_20_32_table.c",
+        "exc_5_256_table.c", "exc_5_64_table.c", "exc_8_128_table.c", "filters.c", "gain_table.c",
+        "gain_table_lbr.c", "hexc_10_32_table.c", "hexc_table.c", "high_lsp_tables.c", "lpc.c", "lsp.c",
+        "lsp_tables_nb.c", "ltp.c", "modes.c", "modes_wb.c", "nb_celp.c", "quant_lsp.c", "sb_celp.c",
+        "speex.c", "speex_callbacks.c", "vbr.c", "vq.c", "window.c",
+    ];
+    cc::Build::new()
+        .include("speex/include")
+        .files(sources.iter().map(|s| format!("speex/libspeex/{}", s)))
+        .define("FLOATING_POINT", None)
+        .define("EXPORT", "")
+        .warnings(false)
+        .compile("speex");
+}
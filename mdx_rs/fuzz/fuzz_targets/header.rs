@@ -0,0 +1,9 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use mdx_rs::fuzzing::parse_header;
+
+// length prefix, utf-16 header xml and its adler32, the first section of every mdx/mdd file
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_header(data);
+});
@@ -0,0 +1,19 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use mdx_rs::fuzzing::{decode_key_block_bytes, KeyBlockInfo};
+
+// bit 0 of byte 0 set means utf-16 keys, bit 1 4-byte key ids as in v1 files, bytes 1-2 the expected decompressed size (little endian), byte 3
+// the expected number of keys, then one key block: type, adler32, zlib or raw key ids and texts
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 4 {
+        return;
+    }
+    let info = KeyBlockInfo {
+        num_entries: data[3] as u64,
+        compressed_size: data.len() - 4,
+        decompressed_size: u16::from_le_bytes([data[1], data[2]]) as usize,
+    };
+    let width = if data[0] & 2 == 2 { 4 } else { 8 };
+    let _ = decode_key_block_bytes(&data[4..], &info, data[0] & 1 == 1, width);
+});
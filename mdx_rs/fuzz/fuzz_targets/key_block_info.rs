@@ -0,0 +1,19 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use mdx_rs::fuzzing::{decode_key_block_info, HeaderBuilder};
+
+// first byte picks the header flags: bit 0 v2 format, bit 1 encrypted key block info, bit 2 utf-16 keys
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    let flags = data[0];
+    let mut hb = HeaderBuilder::default();
+    hb.genversion(if flags & 1 == 1 { 2.0 } else { 1.2 });
+    hb.encrypted(if flags & 2 == 2 { "2".to_string() } else { "0".to_string() });
+    if flags & 4 == 4 {
+        hb.encoding("UTF-16".to_string());
+    }
+    let _ = decode_key_block_info(&data[1..], &hb.build());
+});
@@ -0,0 +1,17 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use mdx_rs::Mdx;
+use mdx_rs::fuzzing::decompress_record_block_bytes;
+
+// 12 bytes of record start, end and block offset, then one record block: type, adler32, data
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 12 {
+        return;
+    }
+    let number = |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as usize;
+    let (start, end, offset) = (number(0), number(4), number(8));
+    let block = &data[12..];
+    let _ = decompress_record_block_bytes(block, 1 << 20);
+    let _ = Mdx::extract_record(block, 1 << 20, start, end, offset);
+});
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::{Captures, Regex};
+
+use crate::dict::Dict;
+use crate::error::Result;
+use crate::render::{Format, join_sections, render};
+
+/// file of notes in `export`'s output directory, media files go in `media/`
+pub const DECK_FILE: &str = "deck.txt";
+
+// first line of the web app's /history/export and /stars/export csv files
+const EXPORT_HEADERS: [&str; 2] = ["word,looked_up_at", "word,starred_at"];
+
+/// what `export` wrote
+pub struct AnkiExport {
+    pub notes: usize,
+    pub media: usize,
+    /// words that were not found, in list order
+    pub missing: Vec<String>,
+}
+
+/// words of a vocabulary list: one per line, blank lines and `#` comments skipped. only the first
+/// column of a csv or tab separated line is used, so the history and stars exports of the web app
+/// work as they are, their header line is skipped
+pub fn read_word_list(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+    for (n, line) in text.lines().map(|l| l.trim()).enumerate() {
+        if line.is_empty() || line.starts_with('#') || (n == 0 && EXPORT_HEADERS.contains(&line)) {
+            continue;
+        }
+        let word = match line.strip_prefix('"') {
+            Some(quoted) => {
+                // up to the closing quote, `""` is a quote
+                let mut word = String::new();
+                let mut chars = quoted.chars().peekable();
+                while let Some(c) = chars.next() {
+                    if c == '"' && chars.next_if_eq(&'"').is_none() {
+                        break;
+                    }
+                    word.push(c);
+                }
+                word
+            }
+            None => line.split(&[',', '\t'][..]).next().unwrap_or(line).trim().to_string(),
+        };
+        if !word.is_empty() && !words.contains(&word) {
+            words.push(word);
+        }
+    }
+    words
+}
+
+/// write an Anki import file of the words to `out/deck.txt`: headword on the front, every entry's
+/// html on the back, found like `mdx lookup` finds them with redirects and base forms. the images,
+/// sounds and css the definitions use are copied from the mdd to `out/media/`, which goes into
+/// Anki's collection.media folder. `sound://` links become `[sound:...]` tags, `entry://` links
+/// plain text. a headword reached from two words of the list is one note
+pub fn export(dict: &Dict, words: &[String], out: &Path) -> Result<AnkiExport> {
+    let mut media = Media::new(dict, out.join("media"));
+    fs::create_dir_all(&media.dir)?;
+    // file headers read by anki 2.1.55 and later, older versions need the same choices in the import dialog
+    let mut deck = format!("#separator:tab\n#html:true\n#notetype:Basic\n#deck:{}\n#columns:Front\tBack\n", dict.title);
+    let mut fronts: Vec<String> = vec![];
+    let mut missing = vec![];
+    for word in words {
+        let entries = dict.lookup(word);
+        let front = match entries.first() {
+            Some(l) => l.headword.clone(),
+            None => {
+                missing.push(word.clone());
+                continue;
+            }
+        };
+        if fronts.contains(&front) {
+            continue;
+        }
+        let definitions: Vec<String> = entries.iter()
+            .map(|l| media.rewrite(&render(&l.definition, Format::Html)))
+            .collect::<Result<_>>()?;
+        deck.push_str(&format!("{}\t{}\n", field(&front), field(&join_sections(&definitions, Format::Html))));
+        fronts.push(front);
+    }
+    fs::write(out.join(DECK_FILE), deck)?;
+    Ok(AnkiExport { notes: fronts.len(), media: media.names.len(), missing })
+}
+
+// quoted, so tabs and newlines of the html stay inside the field
+fn field(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+// resources of the definitions copied to the media directory under flat names
+struct Media<'a> {
+    dict: &'a Dict,
+    dir: PathBuf,
+    /// resource path -> file name in `dir`
+    names: HashMap<String, String>,
+    sound: Regex,
+    entry: Regex,
+    attr: Regex,
+}
+
+impl<'a> Media<'a> {
+    fn new(dict: &'a Dict, dir: PathBuf) -> Media<'a> {
+        Media {
+            dict,
+            dir,
+            names: HashMap::new(),
+            sound: Regex::new(r#"(?is)<a\b[^>]*href\s*=\s*["']sound://([^"']+)["'][^>]*>(.*?)</a>"#).unwrap(),
+            entry: Regex::new(r#"(?is)<a\b[^>]*href\s*=\s*["']entry://[^"']*["'][^>]*>(.*?)</a>"#).unwrap(),
+            attr: Regex::new(r#"(?i)\b(src|href)\s*=\s*(["'])([^"']+)["']"#).unwrap(),
+        }
+    }
+
+    fn rewrite(&mut self, html: &str) -> Result<String> {
+        let mut error = None;
+        // regexes are cheap to clone, the replacement closures need self mutably
+        let (sound, entry, attr) = (self.sound.clone(), self.entry.clone(), self.attr.clone());
+        let html = entry.replace_all(html, "$1").to_string();
+        let html = sound.replace_all(&html, |cap: &Captures| match self.copy(&cap[1]) {
+            Ok(Some(name)) => format!("{} [sound:{}]", &cap[2], name),
+            Ok(None) => cap[2].to_string(),
+            Err(e) => {
+                error.get_or_insert(e);
+                cap[0].to_string()
+            }
+        }).to_string();
+        let html = attr.replace_all(&html, |cap: &Captures| {
+            let value = &cap[3];
+            // urls, data: and javascript: values and in-page anchors are not resources
+            if value.contains(':') || value.starts_with('#') {
+                return cap[0].to_string();
+            }
+            match self.copy(value) {
+                Ok(Some(name)) => format!("{}={}{}{}", &cap[1], &cap[2], name, &cap[2]),
+                Ok(None) => cap[0].to_string(),
+                Err(e) => {
+                    error.get_or_insert(e);
+                    cap[0].to_string()
+                }
+            }
+        }).to_string();
+        match error {
+            Some(e) => Err(e),
+            None => Ok(html),
+        }
+    }
+
+    // file name of a resource in the media directory, None when the dictionary does not have it.
+    // the path is flattened and prefixed with the dictionary id, anki keeps every deck's media together
+    fn copy(&mut self, path: &str) -> Result<Option<String>> {
+        let path = path.trim_start_matches(&['/', '\\'][..]);
+        if let Some(name) = self.names.get(path) {
+            return Ok(Some(name.clone()));
+        }
+        let bytes = match self.dict.resource(path) {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        let name = format!("{}_{}", self.dict.id, path.replace(&['/', '\\'][..], "_"));
+        fs::write(self.dir.join(&name), bytes)?;
+        self.names.insert(path.to_string(), name.clone());
+        Ok(Some(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_word_list;
+
+    #[test]
+    fn export_header_is_skipped() {
+        let csv = "word,looked_up_at\nrun,1600000000\n\"say \"\"hi\"\"\",1600000001\nrun,1600000002\n";
+        assert_eq!(read_word_list(csv), vec!["run", "say \"hi\""]);
+        assert_eq!(read_word_list("word,starred_at\nword,1600000000\n"), vec!["word"]);
+    }
+
+    #[test]
+    fn word_is_a_word() {
+        assert_eq!(read_word_list("word\nlist\n"), vec!["word", "list"]);
+        assert_eq!(read_word_list("# deck\nword,noun\n\nword\twhat\n"), vec!["word"]);
+        // the header only counts as the first line
+        assert_eq!(read_word_list("go\nword,looked_up_at\n"), vec!["go", "word"]);
+    }
+}
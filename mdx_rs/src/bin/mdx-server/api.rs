@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use mdx_rs::{Dict, KeyPage, KeyPattern, Stats};
+use mdx_rs::render::{Format, html_to_text, join_sections, render};
+use serde_derive::Serialize;
+use warp::http::StatusCode;
+use warp::reply::{Json, WithStatus};
+
+use crate::resource::rewrite_links;
+use crate::transcode::Transcoder;
+
+#[derive(Serialize)]
+pub struct DictInfo {
+    pub id: String,
+    pub title: String,
+    pub entries: u64,
+}
+
+/// header metadata listed by /dicts
+#[derive(Serialize)]
+pub struct DictMeta {
+    #[serde(flatten)]
+    pub info: DictInfo,
+    pub description: String,
+    pub creation_date: String,
+    pub engine_version: f32,
+    pub required_engine_version: f32,
+    pub format: String,
+    pub encoding: String,
+    pub attributes: HashMap<String, String>,
+}
+
+#[derive(Serialize)]
+pub struct DictStats {
+    pub id: String,
+    #[serde(flatten)]
+    pub stats: Stats,
+}
+
+/// one entry of the word, homographs are separate entries with the same headword
+#[derive(Serialize)]
+pub struct LookupEntry {
+    pub headword: String,
+    pub definition: String,
+    pub text: String,
+    pub redirects: Vec<String>,
+    /// base form found when the word is not a headword
+    pub matched_form: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct LookupResponse {
+    pub word: String,
+    /// headword, redirects and matched form of the first entry
+    pub headword: String,
+    pub dict: DictInfo,
+    /// html, text or md, see the `format` param
+    pub format: String,
+    /// every entry, joined as sections
+    pub definition: String,
+    pub text: String,
+    pub redirects: Vec<String>,
+    pub matched_form: Option<String>,
+    pub entries: Vec<LookupEntry>,
+    pub elapsed_ms: f64,
+}
+
+// /keys and /reverse page size when `limit` is not given, and the most one page returns
+const DEFAULT_KEYS: usize = 100;
+const MAX_KEYS: usize = 1000;
+
+#[derive(Serialize)]
+pub struct KeysResponse {
+    pub dict: DictInfo,
+    pub pattern: String,
+    pub regex: bool,
+    pub limit: usize,
+    #[serde(flatten)]
+    pub page: KeyPage,
+}
+
+#[derive(Serialize)]
+pub struct ReverseResponse {
+    pub dict: DictInfo,
+    pub q: String,
+    pub limit: usize,
+    /// headwords whose definitions contain the query
+    #[serde(flatten)]
+    pub page: KeyPage,
+}
+
+#[derive(Serialize)]
+pub struct ApiError {
+    pub error: String,
+}
+
+impl DictInfo {
+    pub fn of(dict: &Dict) -> DictInfo {
+        DictInfo {
+            id: dict.id.clone(),
+            title: dict.title.clone(),
+            entries: dict.mdx.num_entries,
+        }
+    }
+}
+
+fn error(status: StatusCode, msg: String) -> WithStatus<Json> {
+    warp::reply::with_status(warp::reply::json(&ApiError { error: msg }), status)
+}
+
+/// `dict` param selects the dictionary by id, the first loaded dictionary is the default
+pub fn find_dict<'a>(dicts: &'a [Arc<Dict>], id: Option<&String>) -> Option<&'a Arc<Dict>> {
+    match id {
+        Some(id) => dicts.iter().find(|d| &d.id == id),
+        None => dicts.first(),
+    }
+}
+
+// dictionary of the `dict` param, 404 for an unknown id and 503 when the server has none loaded
+fn dict_param<'a>(dicts: &'a [Arc<Dict>], p: &HashMap<String, String>) -> Result<&'a Arc<Dict>, WithStatus<Json>> {
+    match (find_dict(dicts, p.get("dict")), p.get("dict")) {
+        (Some(d), _) => Ok(d),
+        (None, Some(id)) => Err(error(StatusCode::NOT_FOUND, format!("unknown dict: {}", id))),
+        (None, None) => Err(error(StatusCode::SERVICE_UNAVAILABLE, "no dictionaries loaded".to_string())),
+    }
+}
+
+/// GET /api/v1/lookup?word=&dict=&format=html|text|md, the lookup runs on the offload pool
+pub async fn lookup(dicts: Arc<Vec<Arc<Dict>>>, transcoder: &Transcoder, p: HashMap<String, String>)
+                    -> mdx_rs::Result<WithStatus<Json>> {
+    let start = Instant::now();
+    let word = match p.get("word") {
+        Some(w) if !w.trim().is_empty() => w.trim().to_string(),
+        _ => return Ok(error(StatusCode::BAD_REQUEST, "missing \"word\" param".to_string())),
+    };
+    let dict = match dict_param(&dicts, &p) {
+        Ok(d) => d,
+        Err(e) => return Ok(e),
+    };
+    let format = match p.get("format").map(|f| Format::parse(f)) {
+        None => Format::Html,
+        Some(Some(f)) => f,
+        Some(None) => return Ok(error(StatusCode::BAD_REQUEST, format!("unknown format: {}", p.get("format").unwrap()))),
+    };
+    let entries: Vec<LookupEntry> = dict.clone().lookup_async(word.clone()).await?.into_iter().map(|l| LookupEntry {
+        definition: match format {
+            Format::Html => render(&rewrite_links(&l.definition, &dict.id, transcoder), format),
+            _ => render(&l.definition, format),
+        },
+        text: html_to_text(&l.definition),
+        headword: l.headword,
+        redirects: l.redirects,
+        matched_form: l.matched_form,
+    }).collect();
+    let first = match entries.first() {
+        Some(e) => e,
+        None => return Ok(error(StatusCode::NOT_FOUND, format!("{} not found in {}", word, dict.id))),
+    };
+    let definitions: Vec<String> = entries.iter().map(|e| e.definition.clone()).collect();
+    let texts: Vec<String> = entries.iter().map(|e| e.text.clone()).collect();
+    let resp = LookupResponse {
+        headword: first.headword.clone(),
+        redirects: first.redirects.clone(),
+        matched_form: first.matched_form.clone(),
+        definition: join_sections(&definitions, format),
+        text: join_sections(&texts, Format::Text),
+        word,
+        dict: DictInfo::of(dict),
+        format: format.name().to_string(),
+        entries,
+        elapsed_ms: start.elapsed().as_secs_f64() * 1000.0,
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&resp), StatusCode::OK))
+}
+
+/// `offset` and `limit` params of /keys and /reverse, the limit capped to MAX_KEYS
+fn page(p: &HashMap<String, String>) -> Result<(usize, usize), WithStatus<Json>> {
+    let number = |name: &str, default: usize| match p.get(name) {
+        Some(n) => n.parse::<usize>().map_err(|_| error(StatusCode::BAD_REQUEST, format!("invalid {}: {}", name, n))),
+        None => Ok(default),
+    };
+    Ok((number("offset", 0)?, number("limit", DEFAULT_KEYS)?.min(MAX_KEYS)))
+}
+
+/// GET /keys?pattern=&regex=true&dict=&offset=&limit=, headwords matching a substring, wildcards or a regex.
+/// no pattern pages through every headword, `limit` is capped to MAX_KEYS
+pub fn keys(dicts: Arc<Vec<Arc<Dict>>>, p: HashMap<String, String>) -> WithStatus<Json> {
+    let dict = match dict_param(&dicts, &p) {
+        Ok(d) => d,
+        Err(e) => return e,
+    };
+    let (offset, limit) = match page(&p) {
+        Ok(page) => page,
+        Err(e) => return e,
+    };
+    let pattern = p.get("pattern").cloned().unwrap_or_default();
+    let regex = p.get("regex").map(|r| r != "false" && r != "0").unwrap_or(false);
+    let matcher = match KeyPattern::parse(&pattern, regex, dict.mdx.header.keycasesensitive) {
+        Ok(m) => m,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    let resp = KeysResponse {
+        dict: DictInfo::of(dict),
+        page: dict.mdx.search_keys(&matcher, offset, limit),
+        pattern,
+        regex,
+        limit,
+    };
+    warp::reply::with_status(warp::reply::json(&resp), StatusCode::OK)
+}
+
+/// GET /reverse?q=&dict=&offset=&limit=, headwords whose definitions contain every word of q.
+/// chinese, japanese and korean queries match by characters and bigrams
+pub fn reverse(dicts: Arc<Vec<Arc<Dict>>>, p: HashMap<String, String>) -> WithStatus<Json> {
+    let q = match p.get("q") {
+        Some(q) if !q.trim().is_empty() => q.trim().to_string(),
+        _ => return error(StatusCode::BAD_REQUEST, "missing \"q\" param".to_string()),
+    };
+    let dict = match dict_param(&dicts, &p) {
+        Ok(d) => d,
+        Err(e) => return e,
+    };
+    let index = match &dict.reverse {
+        Some(index) => index,
+        None => return error(StatusCode::NOT_FOUND, format!("no reverse index for {}, start the server with MDX_REVERSE=1", dict.id)),
+    };
+    let (offset, limit) = match page(&p) {
+        Ok(page) => page,
+        Err(e) => return e,
+    };
+    let resp = ReverseResponse {
+        dict: DictInfo::of(dict),
+        page: index.search(&q, offset, limit),
+        q,
+        limit,
+    };
+    warp::reply::with_status(warp::reply::json(&resp), StatusCode::OK)
+}
+
+/// GET /dicts
+pub fn dicts(dicts: Arc<Vec<Arc<Dict>>>) -> Json {
+    let metas: Vec<DictMeta> = dicts.iter().map(|d| {
+        let h = &d.mdx.header;
+        DictMeta {
+            info: DictInfo::of(d),
+            description: h.description.clone(),
+            creation_date: h.creationdate.clone(),
+            engine_version: h.genversion,
+            required_engine_version: h.requiredversion,
+            format: h.format.clone(),
+            encoding: h.encoding.clone(),
+            attributes: h.attributes.clone(),
+        }
+    }).collect();
+    warp::reply::json(&metas)
+}
+
+/// GET /stats?dict=id, block counts and sizes of one dictionary or of all of them
+pub fn stats(dicts: Arc<Vec<Arc<Dict>>>, p: HashMap<String, String>) -> WithStatus<Json> {
+    let selected: Vec<&Arc<Dict>> = match p.get("dict") {
+        Some(id) => match dicts.iter().find(|d| &d.id == id) {
+            Some(d) => vec![d],
+            None => return error(StatusCode::NOT_FOUND, format!("unknown dict: {}", id)),
+        },
+        None => dicts.iter().collect(),
+    };
+    let stats: Vec<DictStats> = selected.iter()
+        .map(|d| DictStats { id: d.id.clone(), stats: d.mdx.stats() })
+        .collect();
+    warp::reply::with_status(warp::reply::json(&stats), StatusCode::OK)
+}
@@ -0,0 +1,25 @@
+//! http server for mdx dictionaries, `mdx-server [file.mdx]...`
+
+mod api;
+mod resource;
+mod server;
+mod speex;
+mod transcode;
+
+const MDX_PATH: &str = "/home/cod3fn/code/rs-notes/resources/LSC4.mdx";
+
+/// dictionaries to serve, `MDX_PATHS=a.mdx:b.mdx` overrides the default MDX_PATH
+fn mdx_paths() -> Vec<String> {
+    match std::env::var("MDX_PATHS") {
+        Ok(paths) => paths.split(':').filter(|p| !p.is_empty()).map(|p| p.to_string()).collect(),
+        Err(_) => vec![MDX_PATH.to_string()],
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    pretty_env_logger::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let paths = if args.is_empty() { mdx_paths() } else { args };
+    server::run(paths).await
+}
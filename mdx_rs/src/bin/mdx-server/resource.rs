@@ -0,0 +1,308 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+use log::error;
+use mdx_rs::Dict;
+use mdx_rs::render::decode_entities;
+use regex::{Captures, Regex};
+use warp::http::{Response, StatusCode};
+
+use crate::api::find_dict;
+use crate::transcode::Transcoder;
+
+lazy_static! {
+    // a link in a definition: a whole quoted attribute value, keys may have spaces, or an
+    // unquoted value up to the next space
+    static ref LINK: Regex = Regex::new(
+        r#""(sound|entry)://([^"]*)"|'(sound|entry)://([^']*)'|(sound|entry)://([^"'\s>]+)"#).unwrap();
+}
+
+/// content type by file extension, the common resource types found in mdd archives
+pub fn mime_type(path: &str) -> &'static str {
+    let ext = path.rsplit('.').next().unwrap_or("").to_lowercase();
+    match ext.as_str() {
+        "css" => "text/css; charset=UTF-8",
+        "js" => "application/javascript; charset=UTF-8",
+        "html" | "htm" => "text/html; charset=UTF-8",
+        "txt" => "text/plain; charset=UTF-8",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/ogg; codecs=opus",
+        "spx" => "audio/ogg; codecs=speex",
+        "m4a" | "aac" => "audio/aac",
+        "mp4" => "video/mp4",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+/// decode `%XX` escapes in a url path segment, mdd paths are often non-ascii
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// what a `Range` header asks of a body of some length
+#[derive(Debug, PartialEq)]
+enum Range {
+    /// send everything with 200: no header, a malformed one or several ranges, which are not
+    /// supported and may be ignored (RFC 7233 section 3.1)
+    Full,
+    /// the inclusive byte range, 206
+    Partial(usize, usize),
+    /// a valid single range outside the body, 416
+    Unsatisfiable,
+}
+
+/// parse a single `bytes=start-end`, `bytes=start-` or `bytes=-suffix` range
+fn parse_range(range: &str, len: usize) -> Range {
+    let spec = match range.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec,
+        _ => return Range::Full,
+    };
+    let (start, end) = match spec.find('-') {
+        Some(i) => (spec[..i].trim(), spec[i + 1..].trim()),
+        None => return Range::Full,
+    };
+    let number = |s: &str| s.parse::<usize>().ok();
+    match (start, end) {
+        ("", suffix) => match number(suffix) {
+            None => Range::Full,
+            Some(0) => Range::Unsatisfiable,
+            Some(_) if len == 0 => Range::Unsatisfiable,
+            Some(n) => Range::Partial(len - n.min(len), len - 1),
+        },
+        (s, "") => match number(s) {
+            None => Range::Full,
+            Some(s) if s >= len => Range::Unsatisfiable,
+            Some(s) => Range::Partial(s, len - 1),
+        },
+        (s, e) => match (number(s), number(e)) {
+            (Some(s), Some(e)) if s <= e => {
+                if s >= len {
+                    Range::Unsatisfiable
+                } else {
+                    Range::Partial(s, e.min(len - 1))
+                }
+            }
+            _ => Range::Full,
+        },
+    }
+}
+
+/// `If-None-Match` is `*` or a comma separated list of tags, compared weakly
+fn etag_matches(if_none_match: &str, tag: &str) -> bool {
+    if_none_match.split(',')
+        .map(|t| t.trim())
+        .any(|t| t == "*" || t.trim_start_matches("W/") == tag)
+}
+
+fn etag(bytes: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    format!("\"{:x}-{:x}\"", bytes.len(), hasher.finish())
+}
+
+fn status(code: StatusCode) -> Response<Vec<u8>> {
+    Response::builder().status(code).body(vec![]).unwrap()
+}
+
+/// `%XX` escapes for every byte but letters, digits and `-._~`, and `/` when `path` is true
+pub fn percent_encode(s: &str, path: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) || (path && b == b'/') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// point `sound://` and `entry://` links of a definition at this server, the key or path is
+/// percent-encoded. spx sounds get the transcoder's default format so they play in the browser
+pub fn rewrite_links(html: &str, dict_id: &str, transcoder: &Transcoder) -> String {
+    let dict_id = percent_encode(dict_id, false);
+    LINK.replace_all(html, |cap: &Captures| {
+        let (quote, scheme, target) = match (cap.get(1), cap.get(3)) {
+            (Some(scheme), _) => ("\"", scheme.as_str(), &cap[2]),
+            (_, Some(scheme)) => ("'", scheme.as_str(), &cap[4]),
+            _ => ("", &cap[5], &cap[6]),
+        };
+        let target = decode_entities(target.trim_start_matches(&['/', '\\'][..]));
+        let url = match scheme {
+            "entry" => format!("/q?dict={}&key={}", dict_id, percent_encode(&target, false)),
+            _ => {
+                let path = percent_encode(&target.replace('\\', "/"), true);
+                match &transcoder.default_format {
+                    Some(f) if Transcoder::is_spx(&target) => format!("/res/{}/{}?format={}", dict_id, path, f),
+                    _ => format!("/res/{}/{}", dict_id, path),
+                }
+            }
+        };
+        format!("{}{}{}", quote, url, quote)
+    }).to_string()
+}
+
+/// GET /res/{dict}/{path}, the `Range` and `If-None-Match` request headers are honored.
+/// `format=wav|opus` converts a `.spx` resource before sending it, another format is a 400.
+/// reading the mdd and converting run on the offload pool
+pub async fn serve(dicts: Arc<Vec<Arc<Dict>>>, transcoder: Arc<Transcoder>, id: String, tail: &str, format: Option<String>,
+                   range: Option<String>, if_none_match: Option<String>) -> mdx_rs::Result<Response<Vec<u8>>> {
+    let dict = match find_dict(&dicts, Some(&id)) {
+        Some(d) => d.clone(),
+        None => return Ok(status(StatusCode::NOT_FOUND)),
+    };
+    if format.iter().any(|f| !transcoder.supports(f)) {
+        return Ok(status(StatusCode::BAD_REQUEST));
+    }
+    let mut path = percent_decode(tail);
+    let mut bytes = match dict.clone().resource_async(path.clone()).await? {
+        Some(b) => b,
+        None => return Ok(status(StatusCode::NOT_FOUND)),
+    };
+    if let Some(f) = format.filter(|_| Transcoder::is_spx(&path)) {
+        let (spx, converted_path) = (path.clone(), format!("{}.{}", path, f));
+        let convert = move || transcoder.transcode(&dict.id, &spx, &bytes, &f);
+        match mdx_rs::offload(&format!("transcoding {}", path), convert).await? {
+            Ok(converted) => {
+                bytes = converted;
+                path = converted_path;
+            }
+            Err(e) => {
+                error!("transcode {}: {}", path, e);
+                return Ok(status(StatusCode::INTERNAL_SERVER_ERROR));
+            }
+        }
+    }
+
+    let tag = etag(&bytes);
+    let builder = Response::builder()
+        .header("content-type", mime_type(&path))
+        .header("etag", tag.as_str())
+        .header("cache-control", "public, max-age=86400")
+        .header("accept-ranges", "bytes");
+    if if_none_match.iter().any(|m| etag_matches(m, &tag)) {
+        return Ok(builder.status(StatusCode::NOT_MODIFIED).body(vec![]).unwrap());
+    }
+    Ok(match range.map_or(Range::Full, |r| parse_range(&r, bytes.len())) {
+        Range::Partial(start, end) => builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header("content-range", format!("bytes {}-{}/{}", start, end, bytes.len()))
+            .body(bytes[start..=end].to_vec())
+            .unwrap(),
+        Range::Unsatisfiable => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header("content-range", format!("bytes */{}", bytes.len()))
+            .body(vec![])
+            .unwrap(),
+        Range::Full => builder.body(bytes).unwrap(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::transcode::Transcoder;
+
+    use super::{etag_matches, parse_range, percent_decode, percent_encode, Range, rewrite_links};
+
+    #[test]
+    fn single_ranges() {
+        assert_eq!(parse_range("bytes=0-9", 100), Range::Partial(0, 9));
+        assert_eq!(parse_range("bytes=90-", 100), Range::Partial(90, 99));
+        assert_eq!(parse_range("bytes=-10", 100), Range::Partial(90, 99));
+        assert_eq!(parse_range("bytes=-500", 100), Range::Partial(0, 99));
+        assert_eq!(parse_range("bytes=50-500", 100), Range::Partial(50, 99));
+        assert_eq!(parse_range(" bytes= 5 - 6 ", 100), Range::Partial(5, 6));
+    }
+
+    #[test]
+    fn unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=100-", 100), Range::Unsatisfiable);
+        assert_eq!(parse_range("bytes=100-200", 100), Range::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 100), Range::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), Range::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-5", 0), Range::Unsatisfiable);
+    }
+
+    #[test]
+    fn malformed_and_multi_ranges_are_ignored() {
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), Range::Full);
+        assert_eq!(parse_range("bytes=9-1", 100), Range::Full);
+        assert_eq!(parse_range("bytes=a-b", 100), Range::Full);
+        assert_eq!(parse_range("bytes=5", 100), Range::Full);
+        assert_eq!(parse_range("bytes=-", 100), Range::Full);
+        assert_eq!(parse_range("items=0-1", 100), Range::Full);
+    }
+
+    #[test]
+    fn if_none_match_lists() {
+        let tag = "\"10-abc\"";
+        assert!(etag_matches("\"10-abc\"", tag));
+        assert!(etag_matches("*", tag));
+        assert!(etag_matches("\"x\", W/\"10-abc\"", tag));
+        assert!(!etag_matches("\"x\", \"y\"", tag));
+        assert!(!etag_matches("", tag));
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("img/apple.png"), "img/apple.png");
+        assert_eq!(percent_decode("%E8%8B%B9%E6%9E%9C.png"), "苹果.png");
+        assert_eq!(percent_decode("a%20b%2f"), "a b/");
+        assert_eq!(percent_decode("%41"), "A");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn percent_encoding() {
+        assert_eq!(percent_encode("a b&c#d+e", false), "a%20b%26c%23d%2Be");
+        assert_eq!(percent_encode("苹果", false), "%E8%8B%B9%E6%9E%9C");
+        assert_eq!(percent_encode("img/a b.png", true), "img/a%20b.png");
+        assert_eq!(percent_encode("a/b", false), "a%2Fb");
+        assert_eq!(percent_decode(&percent_encode("rock & roll #1", false)), "rock & roll #1");
+    }
+
+    #[test]
+    fn links_are_escaped() {
+        let transcoder = Transcoder { cache_dir: PathBuf::new(), opusenc: None, default_format: None };
+        let html = r#"<a href="entry://rock &amp; roll">x</a><a href='entry://C#'>y</a><a href=entry://a+b>z</a>"#;
+        assert_eq!(rewrite_links(html, "my dict", &transcoder),
+                   r#"<a href="/q?dict=my%20dict&key=rock%20%26%20roll">x</a><a href='/q?dict=my%20dict&key=C%23'>y</a><a href=/q?dict=my%20dict&key=a%2Bb>z</a>"#);
+        let html = r#"<a href="sound://\us\a b.spx">play</a>"#;
+        assert_eq!(rewrite_links(html, "d", &transcoder), r#"<a href="/res/d/us/a%20b.spx">play</a>"#);
+        let wav = Transcoder { default_format: Some("wav".to_string()), ..transcoder };
+        assert_eq!(rewrite_links(html, "d", &wav), r#"<a href="/res/d/us/a%20b.spx?format=wav">play</a>"#);
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use log::{debug, error};
+use mdx_rs::{Dict, Lemmatizer, Mdx, Workers};
+use mdx_rs::render::{self, Format};
+use rusqlite::{Connection, params};
+use warp::{Filter, Reply};
+use warp::http::{Response, StatusCode};
+
+use crate::{api, resource};
+use crate::transcode::Transcoder;
+
+async fn query(dict: Arc<Dict>, transcoder: &Transcoder, word: String, format: Format) -> mdx_rs::Result<String> {
+    debug!("query params={}", &word);
+    let id = dict.id.clone();
+    let entries: Vec<String> = dict.lookup_async(word).await?.iter().map(|l| match format {
+        Format::Html => render::render(&resource::rewrite_links(&l.definition, &id, transcoder), format),
+        _ => render::render(&l.definition, format),
+    }).collect();
+    if entries.is_empty() {
+        return Ok("not found".to_string());
+    }
+    Ok(render::join_sections(&entries, format))
+}
+
+/// the reply of a handler whose lookup ran on the offload pool, a panic there (e.g. on a corrupt
+/// block) becomes a 500 response
+fn or_500<R: Reply>(reply: mdx_rs::Result<R>) -> Result<warp::reply::Response, Infallible> {
+    match reply {
+        Ok(reply) => Ok(reply.into_response()),
+        Err(e) => {
+            error!("request handler failed: {}", e);
+            Ok(warp::reply::with_status("internal server error", StatusCode::INTERNAL_SERVER_ERROR).into_response())
+        }
+    }
+}
+
+fn indexing(db_file: &str, conn: &mut Connection, mdx: &Mdx) {
+    conn.execute(
+        "create table if not exists MDX_INDEX (
+                key_text text not null,
+                file_pos integer,
+                compressed_size integer,
+                decompressed_size integer,
+                record_block_type integer,
+                record_start integer,
+                record_end integer,
+                offset integer,
+                entry integer not null
+         )",
+        params![],
+    ).expect("create db error");
+    // homographs share key_text, `entry` keeps them in file order
+    conn.execute("create index if not exists MDX_INDEX_KEY on MDX_INDEX (key_text, entry)", params![])
+        .expect("create db index error");
+
+    if std::path::PathBuf::from(db_file).exists() {
+        println!("new db created");
+    }
+    let tx = conn.transaction().unwrap();
+    for (entry, r) in mdx.records.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO MDX_INDEX VALUES (?,?,?,?,?,?,?,?,?)",
+            params![
+            r.key_text,
+            r.file_pos as i64,
+            r.compressed_size as i32,
+            r.decompressed_size as i32 ,
+            r.record_block_type as u32,
+            r.record_start as i64,
+            r.record_end as i64,
+            r.offset as i64,
+            entry as i64],
+        ).expect("indexing mdx record info error");
+    }
+    tx.commit().expect("tx commit error");
+    println!("indexing record info done");
+}
+
+/// load the dictionaries, rebuild their sqlite or fst indexes and serve them on localhost:3030
+pub async fn run(paths: Vec<String>) {
+    // a file that fails to parse is logged and left out, the other dictionaries are still served
+    let mut dicts: Vec<Dict> = paths.iter().filter_map(|p| match Dict::open(p) {
+        Ok(d) => Some(d),
+        Err(e) => {
+            error!("skipping {}: {}", p, e);
+            None
+        }
+    }).collect();
+
+    // MDX_LEMMAS=all or a comma separated list of dictionary ids: the english dictionaries whose
+    // lookups try base forms of inflected words. MDX_AFFIX=en_US.aff adds hunspell suffix rules
+    let lemma_dicts = std::env::var("MDX_LEMMAS").unwrap_or_default();
+    let lemma_dicts: Vec<&str> = lemma_dicts.split(',').map(|id| id.trim()).filter(|id| !id.is_empty()).collect();
+    let lemmatizer = match std::env::var("MDX_AFFIX") {
+        Ok(affix) => Lemmatizer::with_affix_file(&affix).unwrap_or_else(|e| {
+            error!("{}: {}, using the built-in rules", affix, e);
+            Lemmatizer::english()
+        }),
+        Err(_) => Lemmatizer::english(),
+    };
+    let lemmatizer = Arc::new(lemmatizer);
+    // MDX_REVERSE=1 reads every definition at startup for /reverse, it takes a while and a lot of
+    // memory on big dictionaries
+    let build_reverse = std::env::var("MDX_REVERSE").map(|v| v == "1").unwrap_or(false);
+    let workers = Workers::from_env();
+    // MDX_INDEX=fst builds the memory-mapped fst index instead of the sqlite one. its lookups follow
+    // KeyCaseSensitive and StripKey of the dictionary, the sqlite index matches headwords exactly.
+    // an up to date fst index, e.g. from `mdx index`, is used in either mode
+    let fst = std::env::var("MDX_INDEX").map(|v| v == "fst").unwrap_or(false);
+    for dict in &mut dicts {
+        dict.load_resources();
+        if lemma_dicts.iter().any(|&id| id == "all" || id == dict.id) {
+            dict.lemmatizer = Some(lemmatizer.clone());
+        }
+        if build_reverse {
+            dict.build_reverse_index(&workers);
+            println!("reverse index of {} built", dict.id);
+        }
+        if fst && dict.fst.is_none() {
+            match dict.build_fst_index() {
+                Ok(()) => println!("fst index of {} built", dict.id),
+                Err(e) => error!("fst index of {}: {}, using the sqlite index", dict.id, e),
+            }
+        }
+        // Dict::open only maps an fst index built from this very mdx file
+        if dict.fst.is_some() {
+            println!("using the fst index of {}", dict.id);
+            continue;
+        }
+        let db_file = &dict.db_file;
+        if std::path::PathBuf::from(db_file).exists() {
+            std::fs::remove_file(db_file).expect("remove old db error");
+            println!("Removing old db file:{}", db_file);
+        }
+        let mut conn = Connection::open(db_file).unwrap();
+        indexing(db_file, &mut conn, &dict.mdx);
+    }
+    let dicts: Arc<Vec<Arc<Dict>>> = Arc::new(dicts.into_iter().map(Arc::new).collect());
+    let with_dicts = warp::any().map(move || dicts.clone());
+    let transcoder = Arc::new(Transcoder::from_env());
+    let with_transcoder = warp::any().map(move || transcoder.clone());
+
+    // get /q?key=value&dict=id&format=html|text|md
+    let query = warp::get()
+        .and(warp::path("q"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_dicts.clone())
+        .and(with_transcoder.clone())
+        .and_then(|p: HashMap<String, String>, dicts: Arc<Vec<Arc<Dict>>>, transcoder: Arc<Transcoder>| async move {
+            let reply = match (p.get("key"), api::find_dict(&dicts, p.get("dict"))) {
+                (Some(key), Some(dict)) => {
+                    let format = p.get("format").and_then(|f| Format::parse(f)).unwrap_or(Format::Html);
+                    query(dict.clone(), &transcoder, key.clone(), format).await.map(|body| Response::builder()
+                        .header("content-type", format.content_type())
+                        .body(body))
+                }
+                (None, _) => Ok(Response::builder().body(String::from("No \"key\" param in query."))),
+                (_, None) => Ok(Response::builder().body(String::from("No such dict."))),
+            };
+            or_500(reply)
+        });
+
+    // get /api/v1/lookup?word=value&dict=id
+    let lookup = warp::get()
+        .and(warp::path!("api" / "v1" / "lookup"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_dicts.clone())
+        .and(with_transcoder.clone())
+        .and_then(|p: HashMap<String, String>, dicts: Arc<Vec<Arc<Dict>>>, transcoder: Arc<Transcoder>| async move {
+            or_500(api::lookup(dicts, &transcoder, p).await)
+        });
+
+    let list = warp::get()
+        .and(warp::path!("dicts"))
+        .and(with_dicts.clone())
+        .map(api::dicts);
+
+    // get /stats?dict=id
+    let stats = warp::get()
+        .and(warp::path!("stats"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_dicts.clone())
+        .map(|p: HashMap<String, String>, dicts: Arc<Vec<Arc<Dict>>>| api::stats(dicts, p));
+
+    // get /keys?pattern=*tion&regex=false&dict=id&offset=0&limit=100
+    let keys = warp::get()
+        .and(warp::path!("keys"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_dicts.clone())
+        .and_then(|p: HashMap<String, String>, dicts: Arc<Vec<Arc<Dict>>>| async move {
+            or_500(mdx_rs::offload("/keys", move || api::keys(dicts, p)).await)
+        });
+
+    // get /reverse?q=银行&dict=id&offset=0&limit=100
+    let reverse = warp::get()
+        .and(warp::path!("reverse"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_dicts.clone())
+        .and_then(|p: HashMap<String, String>, dicts: Arc<Vec<Arc<Dict>>>| async move {
+            or_500(mdx_rs::offload("/reverse", move || api::reverse(dicts, p)).await)
+        });
+
+    // get /res/{dict}/{path}?format=wav, files from the mdd archive or the dictionary directory
+    let res = warp::get()
+        .and(warp::path("res"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::tail())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::header::optional::<String>("range"))
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(with_dicts.clone())
+        .and(with_transcoder.clone())
+        .and_then(|id: String, tail: warp::path::Tail, p: HashMap<String, String>, range: Option<String>, inm: Option<String>,
+                   dicts: Arc<Vec<Arc<Dict>>>, transcoder: Arc<Transcoder>| async move {
+            or_500(resource::serve(dicts, transcoder, id, tail.as_str(), p.get("format").cloned(), range, inm).await)
+        });
+
+    let css = warp::path("LSC4.css").and(warp::fs::file("static/LSC4.css"));
+
+    let routes = query.or(lookup).or(list).or(stats).or(keys).or(reverse).or(res).or(css);
+    println!("server listening on localhost:3030");
+    warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
+}
@@ -0,0 +1,387 @@
+//! ogg speex (`.spx`) to wav with the libspeex bundled in `speex/`, compiled by build.rs.
+//! decodes like speexdec: perceptual enhancement on, the encoder lookahead dropped from the start
+//! and the end trimmed to the granule position of the last page
+
+use std::os::raw::{c_char, c_int, c_void};
+
+// speex.h
+const SPEEX_SET_ENH: c_int = 0;
+const SPEEX_GET_FRAME_SIZE: c_int = 3;
+const SPEEX_SET_SAMPLING_RATE: c_int = 24;
+const SPEEX_GET_LOOKAHEAD: c_int = 39;
+// narrowband, wideband and ultra-wideband
+const SPEEX_NB_MODES: i32 = 3;
+// speexenc packs at most 10 frames in a packet
+const MAX_FRAMES_PER_PACKET: i32 = 10;
+// ten minutes at 48 kHz, pronunciations are a few seconds
+const MAX_SAMPLES: usize = 48000 * 600;
+
+#[repr(C)]
+struct SpeexBits {
+    chars: *mut c_char,
+    nb_bits: c_int,
+    char_ptr: c_int,
+    bit_ptr: c_int,
+    owner: c_int,
+    overflow: c_int,
+    buf_size: c_int,
+    reserved1: c_int,
+    reserved2: *mut c_void,
+}
+
+#[repr(C)]
+struct SpeexMode {
+    _private: [u8; 0],
+}
+
+extern "C" {
+    fn speex_lib_get_mode(mode: c_int) -> *const SpeexMode;
+    fn speex_decoder_init(mode: *const SpeexMode) -> *mut c_void;
+    fn speex_decoder_destroy(state: *mut c_void);
+    fn speex_decoder_ctl(state: *mut c_void, request: c_int, ptr: *mut c_void) -> c_int;
+    fn speex_decode_int(state: *mut c_void, bits: *mut SpeexBits, out: *mut i16) -> c_int;
+    fn speex_bits_init(bits: *mut SpeexBits);
+    fn speex_bits_destroy(bits: *mut SpeexBits);
+    fn speex_bits_read_from(bits: *mut SpeexBits, bytes: *const c_char, len: c_int);
+    fn speex_bits_remaining(bits: *mut SpeexBits) -> c_int;
+}
+
+/// the 80 byte header packet that starts a speex stream
+struct SpeexHeader {
+    rate: i32,
+    mode: i32,
+    frames_per_packet: i32,
+    extra_headers: i32,
+}
+
+impl SpeexHeader {
+    fn parse(packet: &[u8]) -> Result<SpeexHeader, String> {
+        if packet.len() < 80 || &packet[..8] != b"Speex   " {
+            return Err("not a speex stream".to_string());
+        }
+        let field = |offset: usize| i32::from_le_bytes([packet[offset], packet[offset + 1], packet[offset + 2], packet[offset + 3]]);
+        let header = SpeexHeader {
+            rate: field(36),
+            mode: field(40),
+            frames_per_packet: field(64).max(1),
+            extra_headers: field(68),
+        };
+        if header.mode < 0 || header.mode >= SPEEX_NB_MODES {
+            return Err(format!("unknown speex mode {}", header.mode));
+        }
+        if header.rate < 1000 || header.rate > 48000 {
+            return Err(format!("unsupported sample rate {}", header.rate));
+        }
+        if header.frames_per_packet > MAX_FRAMES_PER_PACKET || header.extra_headers < 0 {
+            return Err("invalid speex header".to_string());
+        }
+        Ok(header)
+    }
+}
+
+/// a libspeex decoder state and its bit buffer, both freed on drop
+struct Decoder {
+    state: *mut c_void,
+    bits: SpeexBits,
+    frame_size: usize,
+    lookahead: usize,
+}
+
+impl Decoder {
+    fn new(header: &SpeexHeader) -> Result<Decoder, String> {
+        unsafe {
+            let mode = speex_lib_get_mode(header.mode);
+            if mode.is_null() {
+                return Err(format!("unknown speex mode {}", header.mode));
+            }
+            let state = speex_decoder_init(mode);
+            if state.is_null() {
+                return Err("speex decoder init failed".to_string());
+            }
+            let mut bits = std::mem::zeroed::<SpeexBits>();
+            speex_bits_init(&mut bits);
+            let (mut frame_size, mut lookahead, mut enh, mut rate): (c_int, c_int, c_int, c_int) = (0, 0, 1, header.rate);
+            speex_decoder_ctl(state, SPEEX_SET_ENH, &mut enh as *mut c_int as *mut c_void);
+            speex_decoder_ctl(state, SPEEX_SET_SAMPLING_RATE, &mut rate as *mut c_int as *mut c_void);
+            speex_decoder_ctl(state, SPEEX_GET_FRAME_SIZE, &mut frame_size as *mut c_int as *mut c_void);
+            speex_decoder_ctl(state, SPEEX_GET_LOOKAHEAD, &mut lookahead as *mut c_int as *mut c_void);
+            Ok(Decoder { state, bits, frame_size: frame_size.max(0) as usize, lookahead: lookahead.max(0) as usize })
+        }
+    }
+
+    /// append the samples of up to `frames` frames of the packet, fewer when it ends early
+    fn decode(&mut self, packet: &[u8], frames: i32, out: &mut Vec<i16>) -> Result<(), String> {
+        let mut frame = vec![0i16; self.frame_size];
+        unsafe {
+            speex_bits_read_from(&mut self.bits, packet.as_ptr() as *const c_char, packet.len() as c_int);
+            for _ in 0..frames {
+                match speex_decode_int(self.state, &mut self.bits, frame.as_mut_ptr()) {
+                    -1 => break,
+                    -2 => return Err("corrupted speex stream".to_string()),
+                    _ => {}
+                }
+                if speex_bits_remaining(&mut self.bits) < 0 {
+                    return Err("speex packet overflow".to_string());
+                }
+                out.extend_from_slice(&frame);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Decoder {
+    fn drop(&mut self) {
+        unsafe {
+            speex_bits_destroy(&mut self.bits);
+            speex_decoder_destroy(self.state);
+        }
+    }
+}
+
+/// a packet of the first logical stream, `granule` is set for the last packet ending on a page
+struct Packet {
+    data: Vec<u8>,
+    granule: Option<i64>,
+}
+
+/// the packets of the first logical stream of an ogg file, pages of other streams are skipped
+fn ogg_packets(bytes: &[u8]) -> Result<Vec<Packet>, String> {
+    let mut packets = vec![];
+    let mut partial: Vec<u8> = vec![];
+    let mut serial = None;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let page = &bytes[pos..];
+        if page.len() < 27 || &page[..4] != b"OggS" {
+            return Err(format!("no ogg page at offset {}", pos));
+        }
+        let segments = page[26] as usize;
+        let lacing = page.get(27..27 + segments).ok_or("truncated ogg page")?;
+        let body_len: usize = lacing.iter().map(|&l| l as usize).sum();
+        let page_len = 27 + segments + body_len;
+        if page.len() < page_len {
+            return Err(format!("truncated ogg page at offset {}", pos));
+        }
+        let page = &page[..page_len];
+        let checksum = u32::from_le_bytes([page[22], page[23], page[24], page[25]]);
+        if ogg_crc(page) != checksum {
+            return Err(format!("ogg page checksum mismatch at offset {}", pos));
+        }
+        pos += page_len;
+        let page_serial = u32::from_le_bytes([page[14], page[15], page[16], page[17]]);
+        if *serial.get_or_insert(page_serial) != page_serial {
+            continue;
+        }
+        let granule = i64::from_le_bytes([page[6], page[7], page[8], page[9], page[10], page[11], page[12], page[13]]);
+        let mut body = &page[27 + segments..];
+        let mut last_complete = None;
+        for &l in lacing {
+            partial.extend_from_slice(&body[..l as usize]);
+            body = &body[l as usize..];
+            // a segment shorter than 255 bytes ends the packet
+            if l < 255 {
+                packets.push(Packet { data: std::mem::take(&mut partial), granule: None });
+                last_complete = Some(packets.len() - 1);
+            }
+        }
+        if let Some(i) = last_complete {
+            packets[i].granule = Some(granule);
+        }
+    }
+    Ok(packets)
+}
+
+// crc32 of an ogg page with its checksum field zeroed, polynomial 0x04c11db7 without reflection
+fn ogg_crc(page: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for (i, &b) in page.iter().enumerate() {
+        let b = if (22..26).contains(&i) { 0 } else { b };
+        crc ^= (b as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// 16 bit mono pcm in a riff wave file
+fn wav(samples: &[i16], rate: u32) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    // pcm, one channel, byte rate, block align, bits per sample
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&rate.to_le_bytes());
+    out.extend_from_slice(&(rate * 2).to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    for s in samples {
+        out.extend_from_slice(&s.to_le_bytes());
+    }
+    out
+}
+
+/// decode an ogg speex file to a wav file. stereo streams come out as mono, the in-band
+/// stereo information is skipped
+pub fn spx_to_wav(spx: &[u8]) -> Result<Vec<u8>, String> {
+    let packets = ogg_packets(spx)?;
+    let header = SpeexHeader::parse(&packets.first().ok_or("empty ogg file")?.data)?;
+    let mut decoder = Decoder::new(&header)?;
+    let mut samples = vec![];
+    // the header, the comments and any extra headers come before the audio
+    let audio = packets.iter().skip(2 + header.extra_headers as usize);
+    let mut end = None;
+    for packet in audio {
+        decoder.decode(&packet.data, header.frames_per_packet, &mut samples)?;
+        end = packet.granule.or(end);
+        if samples.len() > MAX_SAMPLES {
+            return Err(format!("more than {} samples", MAX_SAMPLES));
+        }
+    }
+    samples.drain(..decoder.lookahead.min(samples.len()));
+    // the granule position counts the samples after the lookahead
+    if let Some(end) = end.filter(|&e| e >= 0) {
+        samples.truncate(end as usize);
+    }
+    Ok(wav(&samples, header.rate as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    use super::{ogg_crc, ogg_packets, spx_to_wav, SpeexBits, SpeexMode, speex_bits_destroy, speex_bits_init, speex_lib_get_mode};
+
+    extern "C" {
+        fn speex_encoder_init(mode: *const SpeexMode) -> *mut c_void;
+        fn speex_encoder_destroy(state: *mut c_void);
+        fn speex_encoder_ctl(state: *mut c_void, request: c_int, ptr: *mut c_void) -> c_int;
+        fn speex_encode_int(state: *mut c_void, input: *mut i16, bits: *mut SpeexBits) -> c_int;
+        fn speex_bits_reset(bits: *mut SpeexBits);
+        fn speex_bits_insert_terminator(bits: *mut SpeexBits);
+        fn speex_bits_write(bits: *mut SpeexBits, bytes: *mut c_char, max_len: c_int) -> c_int;
+    }
+
+    const RATE: usize = 16000;
+
+    fn raw_page(serial: u32, seq: u32, granule: i64, lacing: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS\0\0".to_vec();
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&seq.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(lacing);
+        page.extend_from_slice(body);
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    // every packet ends on the page
+    fn page(serial: u32, seq: u32, granule: i64, packets: &[&[u8]]) -> Vec<u8> {
+        let mut lacing = vec![];
+        for p in packets {
+            lacing.resize(lacing.len() + p.len() / 255, 255);
+            lacing.push((p.len() % 255) as u8);
+        }
+        raw_page(serial, seq, granule, &lacing, &packets.concat())
+    }
+
+    fn header(rate: i32, mode: i32, frame_size: i32) -> Vec<u8> {
+        let mut h = b"Speex   ".to_vec();
+        h.resize(28, 0);
+        for field in [1, 80, rate, mode, 4, 1, -1, frame_size, 0, 1, 0, 0, 0] {
+            h.extend_from_slice(&field.to_le_bytes());
+        }
+        h
+    }
+
+    // a second of a 440 Hz tone through the bundled wideband encoder, one frame per packet and page
+    fn tone_spx() -> (Vec<u8>, Vec<i16>, usize) {
+        let tone: Vec<i16> = (0..RATE).map(|i| ((i as f64 * 440.0 * 2.0 * std::f64::consts::PI / RATE as f64).sin() * 8000.0) as i16).collect();
+        unsafe {
+            let state = speex_encoder_init(speex_lib_get_mode(1));
+            let (mut frame_size, mut lookahead, mut quality): (c_int, c_int, c_int) = (0, 0, 8);
+            speex_encoder_ctl(state, 3, &mut frame_size as *mut c_int as *mut c_void);
+            speex_encoder_ctl(state, 39, &mut lookahead as *mut c_int as *mut c_void);
+            speex_encoder_ctl(state, 4, &mut quality as *mut c_int as *mut c_void);
+            let mut bits = std::mem::zeroed::<SpeexBits>();
+            speex_bits_init(&mut bits);
+            let mut spx = page(7, 0, 0, &[&header(RATE as i32, 1, frame_size)]);
+            spx.extend(page(7, 1, 0, &[b"\x04\0\0\0test\0\0\0\0"]));
+            for (n, frame) in tone.chunks(frame_size as usize).enumerate() {
+                let mut frame = frame.to_vec();
+                speex_bits_reset(&mut bits);
+                speex_encode_int(state, frame.as_mut_ptr(), &mut bits);
+                speex_bits_insert_terminator(&mut bits);
+                let mut packet = vec![0u8; 2000];
+                let len = speex_bits_write(&mut bits, packet.as_mut_ptr() as *mut c_char, packet.len() as c_int);
+                packet.truncate(len as usize);
+                let granule = (n as i64 + 1) * frame_size as i64 - lookahead as i64;
+                spx.extend(page(7, n as u32 + 2, granule, &[&packet]));
+            }
+            speex_bits_destroy(&mut bits);
+            speex_encoder_destroy(state);
+            (spx, tone, lookahead as usize)
+        }
+    }
+
+    fn samples(wav: &[u8]) -> Vec<i16> {
+        wav[44..].chunks(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect()
+    }
+
+    #[test]
+    fn decodes_to_wav() {
+        let (spx, tone, lookahead) = tone_spx();
+        let wav = spx_to_wav(&spx).unwrap();
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes([wav[24], wav[25], wav[26], wav[27]]), RATE as u32);
+        let decoded = samples(&wav);
+        assert_eq!(decoded.len(), RATE - lookahead);
+        // the lookahead is dropped, the decoded tone lines up with the input up to the few samples
+        // of delay of the wideband filters
+        let input = &tone[2000..RATE - lookahead - 8];
+        let dot = |a: &[i16], b: &[i16]| a.iter().zip(b).map(|(&x, &y)| x as f64 * y as f64).sum::<f64>();
+        let correlation = (1992..2008).map(|start| {
+            let output = &decoded[start..];
+            dot(input, output) / (dot(input, input) * dot(output, output)).sqrt()
+        }).fold(f64::MIN, f64::max);
+        assert!(correlation > 0.99, "correlation {}", correlation);
+    }
+
+    #[test]
+    fn packets_across_pages() {
+        let long = vec![1u8; 600];
+        // 510 bytes without a segment ending the packet, a page of another stream, then the rest of
+        // the packet and one of exactly 255 bytes
+        let mut ogg = raw_page(1, 0, -1, &[255, 255], &long[..510]);
+        ogg.extend(page(2, 0, 9, &[b"other"]));
+        ogg.extend(page(1, 1, 5, &[&long[510..], &[2u8; 255]]));
+        let packets = ogg_packets(&ogg).unwrap();
+        assert_eq!(packets.iter().map(|p| p.data.len()).collect::<Vec<_>>(), vec![600, 255]);
+        assert_eq!(packets.iter().map(|p| p.granule).collect::<Vec<_>>(), vec![None, Some(5)]);
+    }
+
+    #[test]
+    fn bad_files() {
+        let (spx, _, _) = tone_spx();
+        assert!(spx_to_wav(b"RIFF....WAVE").is_err());
+        assert!(spx_to_wav(&spx[..spx.len() - 3]).unwrap_err().contains("truncated"));
+        let mut corrupt = spx.clone();
+        corrupt[40] ^= 1;
+        assert!(spx_to_wav(&corrupt).unwrap_err().contains("checksum"));
+        assert!(spx_to_wav(&page(1, 0, 0, &[b"OpusHead"])).unwrap_err().contains("not a speex"));
+        let mut mode = header(16000, 7, 320);
+        mode.truncate(80);
+        assert!(spx_to_wav(&page(1, 0, 0, &[&mode])).unwrap_err().contains("mode"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::warn;
+
+use crate::speex;
+
+// unique part of temp file names, concurrent requests for one sound convert to different files
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// browsers can't play speex, pronunciations in `.spx` are decoded to wav with the bundled
+/// libspeex, then encoded with `opusenc` when ogg/opus is wanted and the tool is installed.
+/// converted files are cached on disk, a dictionary has a fixed set of sounds
+pub struct Transcoder {
+    pub cache_dir: PathBuf,
+    /// the opusenc command, None when it is not installed and opus is not offered
+    pub opusenc: Option<String>,
+    /// format sound:// links to .spx are rewritten to, None keeps the original spx
+    pub default_format: Option<String>,
+}
+
+impl Transcoder {
+    /// configured by `MDX_CACHE_DIR`, `OPUSENC` and `SPX_FORMAT` (wav, opus or none, default wav).
+    /// links get wav when opus is asked for but opusenc is not installed
+    pub fn from_env() -> Transcoder {
+        let cache_dir = std::env::var("MDX_CACHE_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| std::env::temp_dir().join("mdx_rs_audio"));
+        let opusenc = std::env::var("OPUSENC").unwrap_or_else(|_| "opusenc".to_string());
+        let opusenc = Some(opusenc).filter(|cmd| Transcoder::installed(cmd));
+        let default_format = match std::env::var("SPX_FORMAT") {
+            Ok(f) if f == "none" || f.is_empty() => None,
+            Ok(f) if f == "opus" && opusenc.is_none() => {
+                warn!("opusenc not found, sound:// links to spx files are converted to wav");
+                Some("wav".to_string())
+            }
+            Ok(f) => Some(f),
+            Err(_) => Some("wav".to_string()),
+        };
+        Transcoder { cache_dir, opusenc, default_format }
+    }
+
+    // the tool can be started, it prints its version and exits
+    fn installed(cmd: &str) -> bool {
+        Command::new(cmd).arg("--version").stdout(Stdio::null()).stderr(Stdio::null()).status().is_ok()
+    }
+
+    pub fn is_spx(path: &str) -> bool {
+        path.to_lowercase().ends_with(".spx")
+    }
+
+    /// whether `transcode` can produce `format`: wav always, opus with opusenc
+    pub fn supports(&self, format: &str) -> bool {
+        format == "wav" || (format == "opus" && self.opusenc.is_some())
+    }
+
+    fn cache_file(&self, dict_id: &str, path: &str, ext: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        self.cache_dir.join(format!("{}-{:x}.{}", dict_id, hasher.finish(), ext))
+    }
+
+    fn run(cmd: &str, args: &[&std::ffi::OsStr]) -> Result<(), String> {
+        let status = Command::new(cmd).args(args).status()
+            .map_err(|e| format!("can not run {}: {}", cmd, e))?;
+        if !status.success() {
+            return Err(format!("{} exited with {}", cmd, status));
+        }
+        Ok(())
+    }
+
+    /// spx bytes to `format`, one that `supports`, returns the converted bytes. files are written
+    /// to temp files that are renamed into the cache when complete, a file in the cache is never partial
+    pub fn transcode(&self, dict_id: &str, path: &str, spx: &[u8], format: &str) -> Result<Vec<u8>, String> {
+        if !self.supports(format) {
+            return Err(format!("unsupported audio format: {}", format));
+        }
+        let target = self.cache_file(dict_id, path, format);
+        if let Ok(bytes) = std::fs::read(&target) {
+            return Ok(bytes);
+        }
+        std::fs::create_dir_all(&self.cache_dir).map_err(|e| e.to_string())?;
+
+        let wav_file = self.cache_file(dict_id, path, "wav");
+        if !wav_file.exists() {
+            let wav = speex::spx_to_wav(spx)?;
+            convert(&wav_file, |tmp| std::fs::write(tmp, &wav).map_err(|e| e.to_string()))?;
+        }
+        if let (Some(opusenc), "opus") = (&self.opusenc, format) {
+            convert(&target, |tmp| Transcoder::run(opusenc, &["--quiet".as_ref(), wav_file.as_os_str(), tmp.as_os_str()]))?;
+        }
+        std::fs::read(&target).map_err(|e| e.to_string())
+    }
+}
+
+// `dir/name.ext` -> `dir/name.<pid>-<n>.tmp.ext`, opusenc picks the format by extension
+fn tmp_file(file: &Path) -> PathBuf {
+    let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let ext = file.extension().and_then(|s| s.to_str()).unwrap_or("");
+    file.with_file_name(format!("{}.{}-{}.tmp.{}", stem, std::process::id(), n, ext))
+}
+
+// run `write` on a temp file and rename it to `file`, the temp file is removed on failure
+fn convert<F: FnOnce(&Path) -> Result<(), String>>(file: &Path, write: F) -> Result<(), String> {
+    let tmp = tmp_file(file);
+    let result = write(&tmp).and_then(|_| std::fs::rename(&tmp, file).map_err(|e| e.to_string()));
+    if result.is_err() {
+        std::fs::remove_file(&tmp).ok();
+    }
+    result
+}
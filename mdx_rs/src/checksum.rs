@@ -0,0 +1,9 @@
+use adler32::RollingAdler32;
+
+use crate::unpack::{Endian, unpack_u32};
+
+/// whether `adler32_bytes`, 4 bytes in `endian` order, hold the adler32 of `contents`.
+/// the header checksum is little endian, every other checksum in the file is big endian
+pub fn adler32_checksum(contents: &[u8], adler32_bytes: &[u8], endian: Endian) -> bool {
+    adler32_bytes.len() == 4 && RollingAdler32::from_buffer(contents).hash() == unpack_u32(adler32_bytes, endian)
+}
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use mdx_rs::{anki, Dict, FstIndex, KeyPattern, Lemmatizer, Lookup, Mdx, Workers};
+use mdx_rs::render::{Format, html_to_text, render};
+
+const USAGE: &str = "usage: mdx <command> [args]
+
+commands:
+    lookup <file.mdx> <word>...   print the definition of each word
+    info <file.mdx> [--raw]       header fields and entry/block counts, --raw lists every header attribute
+    keys <file.mdx> [pattern]     list headwords, optionally only those containing pattern,
+                                  matching it as a wildcard (`*tion`, `re?d`) or with --regex as a regex
+    dump <file.mdx>               print every headword and its definition
+    verify <file.mdx>             check checksums and entry counts
+    index <file.mdx>              build the fst index used by lookup, repl and mdx-server
+    bench <file.mdx> [--workers N]  time loading and a full export, sequential and on N threads
+    repl <file.mdx>...            interactive lookups with history and tab completion
+    anki <file.mdx> <words.txt>   anki deck of the words in the file (- for stdin) and their media, see --out
+
+options:
+    --format text|md|html         definition output format, default text
+    --limit N                     print at most N headwords
+    --offset N                    skip the first N matching headwords
+    --regex                       keys pattern is a regular expression
+    --lemmas                      look up the base forms of inflected english words (went -> go)
+    --affix FILE                  hunspell .aff file with more suffix rules for --lemmas, implies it
+    --out DIR                     anki output directory, default anki
+    --workers N                   block decoding threads, default MDX_WORKERS or one per cpu
+    --no-color                    plain output, also when NO_COLOR is set
+
+the http server is a separate binary: mdx-server [file.mdx]...";
+
+// options taking a value, `--format md` or `--format=md`
+const VALUE_FLAGS: [&str; 6] = ["affix", "format", "limit", "offset", "out", "workers"];
+
+/// command line split into command, positional args and `--flags`
+pub struct Args {
+    pub command: String,
+    pub positional: Vec<String>,
+    pub flags: HashMap<String, String>,
+}
+
+impl Args {
+    pub fn parse(args: Vec<String>) -> Args {
+        let mut command = String::new();
+        let mut positional = vec![];
+        let mut flags = HashMap::new();
+        let mut iter = args.into_iter();
+        while let Some(arg) = iter.next() {
+            if let Some(flag) = arg.strip_prefix("--") {
+                let (name, value) = match flag.find('=') {
+                    Some(eq) => (flag[..eq].to_string(), flag[eq + 1..].to_string()),
+                    None if VALUE_FLAGS.contains(&flag) => (flag.to_string(), iter.next().unwrap_or_default()),
+                    None => (flag.to_string(), String::new()),
+                };
+                flags.insert(name, value);
+            } else if command.is_empty() {
+                command = arg;
+            } else {
+                positional.push(arg);
+            }
+        }
+        Args { command, positional, flags }
+    }
+
+    pub fn has(&self, flag: &str) -> bool {
+        self.flags.contains_key(flag)
+    }
+
+    pub fn format(&self) -> Result<Format, String> {
+        match self.flags.get("format") {
+            Some(f) => Format::parse(f).ok_or(format!("unknown format: {}", f)),
+            None => Ok(Format::Text),
+        }
+    }
+
+    pub fn workers(&self) -> Result<Workers, String> {
+        match self.flags.get("workers") {
+            Some(n) => n.parse::<usize>().map(Workers::new).map_err(|_| format!("invalid workers: {}", n)),
+            None => Ok(Workers::from_env()),
+        }
+    }
+
+    pub fn limit(&self) -> Result<usize, String> {
+        match self.flags.get("limit") {
+            Some(n) => n.parse::<usize>().map_err(|_| format!("invalid limit: {}", n)),
+            None => Ok(usize::MAX),
+        }
+    }
+
+    /// dictionary with base form lookups when asked for by `--lemmas` or `--affix`
+    pub fn open_dict(&self, file: &str) -> Result<Dict, String> {
+        let mut dict = Dict::open(file).map_err(|e| format!("{}: {}", file, e))?;
+        if let Some(affix) = self.flags.get("affix") {
+            let lemmatizer = Lemmatizer::with_affix_file(affix).map_err(|e| format!("{}: {}", affix, e))?;
+            dict.lemmatizer = Some(Arc::new(lemmatizer));
+        } else if self.has("lemmas") {
+            dict.lemmatizer = Some(Arc::new(Lemmatizer::english()));
+        }
+        Ok(dict)
+    }
+
+    pub fn offset(&self) -> Result<usize, String> {
+        match self.flags.get("offset") {
+            Some(n) => n.parse::<usize>().map_err(|_| format!("invalid offset: {}", n)),
+            None => Ok(0),
+        }
+    }
+}
+
+/// ansi styling, disabled when stdout is not a terminal
+pub struct Style {
+    color: bool,
+}
+
+impl Style {
+    pub fn new(args: &Args) -> Style {
+        let color = atty::is(atty::Stream::Stdout) && std::env::var_os("NO_COLOR").is_none() && !args.has("no-color");
+        Style { color }
+    }
+
+    fn paint(&self, s: &str, code: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, s)
+        } else {
+            s.to_string()
+        }
+    }
+
+    pub fn headword(&self, s: &str) -> String {
+        self.paint(s, "1;36")
+    }
+
+    pub fn label(&self, s: &str) -> String {
+        self.paint(s, "1")
+    }
+
+    pub fn dim(&self, s: &str) -> String {
+        self.paint(s, "2")
+    }
+
+    pub fn ok(&self, s: &str) -> String {
+        self.paint(s, "32")
+    }
+
+    pub fn error(&self, s: &str) -> String {
+        self.paint(s, "31")
+    }
+}
+
+/// run a cli command, returns the process exit code
+pub fn run(args: &Args) -> i32 {
+    let style = Style::new(args);
+    if args.has("help") || args.command == "help" {
+        println!("{}", USAGE);
+        return 0;
+    }
+    let file = match args.positional.first() {
+        Some(f) => f.clone(),
+        None => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    let result = match args.command.as_str() {
+        "lookup" => lookup(&file, &args.positional[1..], args, &style),
+        "info" => info(&file, args, &style),
+        "keys" => keys(&file, args.positional.get(1), args),
+        "dump" => dump(&file, args, &style),
+        "verify" => verify(&file, &style),
+        "index" => index(&file, &style),
+        "bench" => bench(&file, args, &style),
+        "repl" => crate::repl::run(args, &style),
+        "anki" => anki(&file, args.positional.get(1), args, &style),
+        c => Err(format!("unknown command: {}\n\n{}", c, USAGE)),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", style.error(&e));
+            2
+        }
+    }
+}
+
+fn lookup(file: &str, words: &[String], args: &Args, style: &Style) -> Result<i32, String> {
+    if words.is_empty() {
+        return Err("lookup needs at least one word".to_string());
+    }
+    let format = args.format()?;
+    let dict = args.open_dict(file)?;
+    let mut code = 0;
+    for word in words {
+        let entries = dict.lookup(word);
+        if entries.is_empty() {
+            eprintln!("{}", style.error(&format!("{}: not found", word)));
+            code = 1;
+        }
+        for (i, l) in entries.iter().enumerate() {
+            println!("{}\n{}\n", entry_title(l, i, entries.len(), style), render(&l.definition, format));
+        }
+    }
+    Ok(code)
+}
+
+/// headword of the i-th of n entries, numbered when there are homographs, and the redirects that led to it
+pub fn entry_title(l: &Lookup, i: usize, n: usize, style: &Style) -> String {
+    let mut title = style.headword(&l.headword);
+    if n > 1 {
+        title.push_str(&style.dim(&format!(" {}/{}", i + 1, n)));
+    }
+    if let Some(form) = &l.matched_form {
+        title.push_str(&style.dim(&format!("  (found as {})", form)));
+    }
+    if !l.redirects.is_empty() {
+        title.push_str(&style.dim(&format!("  (from {})", l.redirects.join(" → "))));
+    }
+    title
+}
+
+fn info(file: &str, args: &Args, style: &Style) -> Result<i32, String> {
+    let mdx = Mdx::open(file).map_err(|e| format!("{}: {}", file, e))?;
+    let h = &mdx.header;
+    if args.has("raw") {
+        let mut names: Vec<&String> = h.attributes.keys().collect();
+        names.sort();
+        for name in names {
+            println!("{}={}", style.label(name), h.attributes[name]);
+        }
+        return Ok(0);
+    }
+    let stats = mdx.stats();
+    let rows: Vec<(&str, String)> = vec![
+        ("file", h.file.clone()),
+        ("title", h.title.clone()),
+        ("description", html_to_text(&h.description).replace('\n', " ")),
+        ("engine version", h.genversion.to_string()),
+        ("required version", h.requiredversion.to_string()),
+        ("format", h.format.clone()),
+        ("encoding", h.encoding.clone()),
+        ("encrypted", h.encrypted.clone()),
+        ("register by", h.registerby.clone()),
+        ("creation date", h.creationdate.clone()),
+        ("key case sensitive", h.keycasesensitive.to_string()),
+        ("strip key", h.stripkey.to_string()),
+        ("compact", h.compact.to_string()),
+        ("compat", h.compat.to_string()),
+        ("left to right", h.left2right.to_string()),
+        ("data source format", h.datasourceformat.clone()),
+        ("stylesheet styles", h.styles.len().to_string()),
+        ("entries", stats.entries.to_string()),
+        ("keys", mdx.keys.len().to_string()),
+        ("key blocks", stats.key_blocks.to_string()),
+        ("record blocks", stats.record_blocks.to_string()),
+        ("file size", stats.file_size.to_string()),
+        ("key block bytes", format!("{} compressed, {} decompressed", stats.key_blocks_compressed, stats.key_blocks_decompressed)),
+        ("record block bytes", format!("{} compressed, {} decompressed", stats.record_blocks_compressed, stats.record_blocks_decompressed)),
+        ("compression ratio", format!("{:.2}", stats.compression_ratio)),
+    ];
+    for (name, value) in rows {
+        println!("{}  {}", style.label(&format!("{:>20}", name)), value);
+    }
+    Ok(0)
+}
+
+fn keys(file: &str, pattern: Option<&String>, args: &Args) -> Result<i32, String> {
+    let limit = args.limit()?;
+    let offset = args.offset()?;
+    let mdx = Mdx::open(file).map_err(|e| format!("{}: {}", file, e))?;
+    let pattern = KeyPattern::parse(pattern.map(|p| p.as_str()).unwrap_or(""), args.has("regex"), mdx.header.keycasesensitive)
+        .map_err(|e| e.to_string())?;
+    let matched = mdx.matching_keys(&pattern).skip(offset).take(limit);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for k in matched {
+        // stop quietly when the reader of a pipe (head, less) goes away
+        if writeln!(out, "{}", k.key_text).is_err() {
+            break;
+        }
+    }
+    Ok(0)
+}
+
+fn dump(file: &str, args: &Args, style: &Style) -> Result<i32, String> {
+    let format = args.format()?;
+    let workers = args.workers()?;
+    let mdx = Mdx::open_with(file, &workers).map_err(|e| format!("{}: {}", file, e))?;
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for (headword, def) in mdx.par_entries(&workers) {
+        if writeln!(out, "{}\n{}\n", style.headword(&headword), render(&def, format)).is_err() {
+            break;
+        }
+    }
+    Ok(0)
+}
+
+fn verify(file: &str, style: &Style) -> Result<i32, String> {
+    let report = Mdx::verify(file);
+    for p in &report.problems {
+        println!("{} {}", style.error("FAIL"), p);
+    }
+    let summary = format!("{}: {} key blocks, {} record blocks, {} entries checked",
+                          file, report.key_blocks_checked, report.record_blocks_checked, report.entries);
+    if report.is_ok() {
+        println!("{} {}", style.ok("OK"), summary);
+        return Ok(0);
+    }
+    println!("{} {}, {} problems", style.error("FAIL"), summary, report.problems.len());
+    Ok(1)
+}
+
+fn index(file: &str, style: &Style) -> Result<i32, String> {
+    let mdx = Mdx::open(file).map_err(|e| format!("{}: {}", file, e))?;
+    let start = Instant::now();
+    let index = FstIndex::build(&mdx).map_err(|e| format!("{}: {}", file, e))?;
+    let (fst_file, rec_file) = FstIndex::paths(file);
+    let size = |f: &str| std::fs::metadata(f).map(|m| m.len()).unwrap_or(0);
+    println!("{} {} records in {:.1} ms, {} {} bytes, {} {} bytes", style.ok("OK"), index.len(),
+             start.elapsed().as_secs_f64() * 1000.0, fst_file, size(&fst_file), rec_file, size(&rec_file));
+    Ok(0)
+}
+
+fn anki(file: &str, words_file: Option<&String>, args: &Args, style: &Style) -> Result<i32, String> {
+    let words_file = words_file.ok_or("anki needs a word list file")?;
+    let text = if words_file == "-" {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text).map(|_| text)
+    } else {
+        std::fs::read_to_string(words_file)
+    }.map_err(|e| format!("{}: {}", words_file, e))?;
+    let words = anki::read_word_list(&text);
+    let mut dict = args.open_dict(file)?;
+    dict.load_resources();
+    let out = Path::new(args.flags.get("out").map(|o| o.as_str()).unwrap_or("anki"));
+    let export = anki::export(&dict, &words, out).map_err(|e| format!("{}: {}", out.display(), e))?;
+    for word in &export.missing {
+        eprintln!("{}", style.error(&format!("{}: not found", word)));
+    }
+    println!("{} {} notes and {} media files from {} words in {}, import {} in anki and copy media/ into its collection.media folder",
+             style.ok("OK"), export.notes, export.media, words.len(), out.display(), anki::DECK_FILE);
+    Ok(if export.missing.is_empty() { 0 } else { 1 })
+}
+
+/// load the file and read every entry, once on the calling thread and once on the pool
+fn bench(file: &str, args: &Args, style: &Style) -> Result<i32, String> {
+    let workers = args.workers()?;
+    let sequential = Workers::new(1);
+    let mut rows = vec![];
+    for (name, w) in [("1 thread", &sequential), ("pool", &workers)].iter() {
+        let start = Instant::now();
+        let mdx = Mdx::open_with(file, w).map_err(|e| format!("{}: {}", file, e))?;
+        let load = start.elapsed();
+        let start = Instant::now();
+        let bytes: usize = mdx.par_entries(w).map(|(_, def)| def.len()).sum();
+        let export = start.elapsed();
+        rows.push((format!("{} ({})", name, w.count()), load, export, mdx.num_entries, bytes));
+    }
+    for (name, load, export, entries, bytes) in &rows {
+        println!("{}  load {:>8.1} ms  export {:>8.1} ms  {} entries, {} definition bytes",
+                 style.label(&format!("{:>12}", name)),
+                 load.as_secs_f64() * 1000.0, export.as_secs_f64() * 1000.0, entries, bytes);
+    }
+    let speedup = |a: Duration, b: Duration| a.as_secs_f64() / b.as_secs_f64().max(1e-9);
+    println!("{}  load x{:.2}  export x{:.2}", style.label(&format!("{:>12}", "speedup")),
+             speedup(rows[0].1, rows[1].1), speedup(rows[0].2, rows[1].2));
+    Ok(0)
+}
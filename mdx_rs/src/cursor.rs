@@ -0,0 +1,194 @@
+use std::io::Read;
+
+use crate::error::{MdxError, Result};
+use crate::unpack::{Endian, unpack_u64};
+
+/// position in a byte slice or a reader, every read is checked against the end of the input
+/// before anything is allocated. `number` reads at the dictionary's number width: 8 bytes in v2
+/// files, 4 in v1. `pos` is the offset reported in `MdxError::Truncated`
+pub struct Cursor<R> {
+    input: R,
+    pos: u64,
+    len: u64,
+    width: usize,
+}
+
+impl<'a> Cursor<&'a [u8]> {
+    pub fn new(bytes: &'a [u8]) -> Cursor<&'a [u8]> {
+        Cursor { input: bytes, pos: 0, len: bytes.len() as u64, width: 8 }
+    }
+
+    /// the next `n` bytes, borrowed from the slice
+    pub fn take(&mut self, n: usize, what: &'static str) -> Result<&'a [u8]> {
+        self.check(n as u64, what)?;
+        let (head, tail) = self.input.split_at(n);
+        self.input = tail;
+        self.pos += n as u64;
+        Ok(head)
+    }
+
+    pub fn skip(&mut self, n: usize, what: &'static str) -> Result<()> {
+        self.take(n, what).map(|_| ())
+    }
+
+    /// everything after the cursor, the cursor ends up at the end
+    pub fn rest(&mut self) -> &'a [u8] {
+        let rest = self.input;
+        self.input = &[];
+        self.pos = self.len;
+        rest
+    }
+
+    /// bytes up to `delimiter`, which is consumed but not returned. the delimiter is only matched
+    /// at multiples of its length from the cursor, so `\0\0` ends utf-16 text on a character boundary
+    pub fn take_until(&mut self, delimiter: &[u8], what: &'static str) -> Result<&'a [u8]> {
+        let end = self.input.chunks_exact(delimiter.len())
+            .position(|unit| unit == delimiter)
+            .map(|i| i * delimiter.len())
+            .ok_or_else(|| MdxError::Invalid(format!("{} at offset {} has no terminator", what, self.pos)))?;
+        let text = self.take(end, what)?;
+        self.skip(delimiter.len(), what)?;
+        Ok(text)
+    }
+}
+
+impl<R: Read> Cursor<R> {
+    /// cursor over a reader with `len` bytes left in it, the file length for a file
+    pub fn with_len(reader: R, len: u64) -> Cursor<R> {
+        Cursor { input: reader, pos: 0, len, width: 8 }
+    }
+
+    /// width in bytes of the numbers read by `number`
+    pub fn width(mut self, width: usize) -> Cursor<R> {
+        self.width = width;
+        self
+    }
+
+    pub fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.len - self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.len
+    }
+
+    /// the next `n` bytes copied out of the input, for sections of a file
+    pub fn read(&mut self, n: u64, what: &'static str) -> Result<Vec<u8>> {
+        self.check(n, what)?;
+        let mut buf = vec![0; n as usize];
+        self.fill(&mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn u8(&mut self, what: &'static str) -> Result<u8> {
+        self.uint(1, Endian::BE, what).map(|n| n as u8)
+    }
+
+    pub fn u16(&mut self, endian: Endian, what: &'static str) -> Result<u16> {
+        self.uint(2, endian, what).map(|n| n as u16)
+    }
+
+    pub fn u32(&mut self, endian: Endian, what: &'static str) -> Result<u32> {
+        self.uint(4, endian, what).map(|n| n as u32)
+    }
+
+    /// big endian number of `width` bytes, counts and sizes in the file
+    pub fn number(&mut self, what: &'static str) -> Result<u64> {
+        self.uint(self.width, Endian::BE, what)
+    }
+
+    fn uint(&mut self, n: usize, endian: Endian, what: &'static str) -> Result<u64> {
+        let mut buf = [0; 8];
+        self.check(n as u64, what)?;
+        self.fill(&mut buf[..n])?;
+        Ok(unpack_u64(&buf[..n], endian))
+    }
+
+    fn check(&self, n: u64, what: &'static str) -> Result<()> {
+        if n > self.remaining() {
+            return Err(MdxError::Truncated { what, offset: self.pos, len: n, available: self.remaining() });
+        }
+        Ok(())
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.input.read_exact(buf)?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cursor;
+    use crate::error::MdxError;
+    use crate::unpack::Endian;
+
+    #[test]
+    fn numbers_at_v1_and_v2_width() {
+        let bytes = [0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 3, 4];
+        let mut v2 = Cursor::new(&bytes[..]);
+        assert_eq!(v2.number("n").unwrap(), 0x0102);
+        assert_eq!(v2.pos(), 8);
+        let mut v1 = Cursor::new(&bytes[..]).width(4);
+        assert_eq!(v1.number("n").unwrap(), 0);
+        assert_eq!(v1.number("n").unwrap(), 0x0102);
+        assert_eq!(v1.number("n").unwrap(), 0x0304);
+        assert!(v1.is_empty());
+    }
+
+    #[test]
+    fn endian_reads() {
+        let mut cursor = Cursor::new(&[1, 2, 1, 2, 0, 0, 0, 9][..]);
+        assert_eq!(cursor.u16(Endian::BE, "n").unwrap(), 0x0102);
+        assert_eq!(cursor.u16(Endian::LE, "n").unwrap(), 0x0201);
+        assert_eq!(cursor.u32(Endian::BE, "n").unwrap(), 9);
+        assert_eq!(cursor.remaining(), 0);
+    }
+
+    #[test]
+    fn take_until_utf8_and_utf16() {
+        let mut utf8 = Cursor::new(&b"ab\0c\0"[..]);
+        assert_eq!(utf8.take_until(b"\0", "key").unwrap(), b"ab");
+        assert_eq!(utf8.take_until(b"\0", "key").unwrap(), b"c");
+        assert!(utf8.is_empty());
+        // "Ā" is 00 01 in utf-16le, the 00 00 across its boundary with "a" (61 00) is not a terminator
+        let utf16 = [0x61, 0x00, 0x00, 0x01, 0x00, 0x00, 0x62, 0x00, 0x00, 0x00];
+        let mut cursor = Cursor::new(&utf16[..]);
+        assert_eq!(cursor.take_until(b"\0\0", "key").unwrap(), &utf16[..4]);
+        assert_eq!(cursor.take_until(b"\0\0", "key").unwrap(), b"b\0");
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn take_until_without_terminator() {
+        let mut cursor = Cursor::new(&b"abc"[..]);
+        match cursor.take_until(b"\0", "key") {
+            Err(MdxError::Invalid(msg)) => assert_eq!(msg, "key at offset 0 has no terminator"),
+            r => panic!("{:?}", r),
+        }
+        // an odd trailing byte can not hold a utf-16 terminator
+        assert!(Cursor::new(&[0x61, 0x00, 0x00][..]).take_until(b"\0\0", "key").is_err());
+    }
+
+    #[test]
+    fn truncation_errors() {
+        let mut cursor = Cursor::new(&[1, 2, 3, 4, 5, 6][..]);
+        cursor.skip(2, "head").unwrap();
+        match cursor.number("key id") {
+            Err(MdxError::Truncated { what, offset, len, available }) => {
+                assert_eq!((what, offset, len, available), ("key id", 2, 8, 4));
+            }
+            r => panic!("{:?}", r),
+        }
+        // nothing was consumed by the failed read
+        assert_eq!(cursor.pos(), 2);
+        assert!(matches!(cursor.take(5, "block"), Err(MdxError::Truncated { len: 5, available: 4, .. })));
+        assert!(matches!(cursor.read(u64::MAX, "block"), Err(MdxError::Truncated { .. })));
+        assert_eq!(cursor.take(4, "block").unwrap(), &[3, 4, 5, 6]);
+    }
+}
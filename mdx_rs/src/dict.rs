@@ -0,0 +1,230 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use log::{debug, info, warn};
+use rusqlite::{Connection, named_params};
+
+use crate::error::Result;
+use crate::fst_index::FstIndex;
+use crate::lemma::Lemmatizer;
+use crate::mdd::Mdd;
+use crate::mdx::{Mdx, RecordIndex};
+use crate::reverse::ReverseIndex;
+use crate::workers::{offload, Workers};
+
+// mdx redirect record, the rest of the record is the target headword
+pub(crate) const LINK_PREFIX: &str = "@@@LINK=";
+// stop following @@@LINK after this many hops, some dictionaries have link cycles
+const MAX_REDIRECTS: usize = 5;
+// Title the MdxBuilder writes when the author left it empty
+const PLACEHOLDER_TITLE: &str = "Title (No HTML code allowed)";
+
+/// one loaded dictionary: the parsed mdx and its sqlite or fst index
+pub struct Dict {
+    /// file stem of the mdx, used as the `dict` param in urls
+    pub id: String,
+    pub title: String,
+    pub mdx: Mdx,
+    pub db_file: String,
+    /// the memory-mapped `<file>.fst` index when it was built, used instead of the sqlite index
+    pub fst: Option<FstIndex>,
+    /// words of the definitions, built by `build_reverse_index`
+    pub reverse: Option<ReverseIndex>,
+    /// base forms tried when a word is not a headword. None unless set for the dictionary, the
+    /// english rules would find wrong entries in dictionaries of other languages
+    pub lemmatizer: Option<Arc<Lemmatizer>>,
+    /// `name.mdd`, `name.1.mdd`, `name.2.mdd`... next to the mdx
+    pub mdds: Vec<Mdd>,
+    /// directory of the mdx, resources missing from the mdd are looked up here
+    pub dir: PathBuf,
+}
+
+/// result of a lookup after following redirects
+pub struct Lookup {
+    /// the headword whose record was finally returned
+    pub headword: String,
+    pub definition: String,
+    /// every headword visited before `headword`, starting with the queried word
+    pub redirects: Vec<String>,
+    /// base form that was found when the queried word itself is not a headword, `run` for `running`
+    pub matched_form: Option<String>,
+}
+
+impl Dict {
+    pub fn open(file: &str) -> Result<Dict> {
+        let mdx = Mdx::open(file)?;
+        let id = Path::new(file).file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| file.to_string());
+        let mut db_file = file.to_string();
+        db_file.push_str(".db");
+        let dir = Path::new(file).parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let title = match mdx.header.title.trim() {
+            "" | PLACEHOLDER_TITLE => id.clone(),
+            t => t.to_string(),
+        };
+        let fst = match FstIndex::open(file) {
+            Ok(index) => Some(index),
+            Err(e) => {
+                debug!("no fst index for {}: {}", file, e);
+                None
+            }
+        };
+        Ok(Dict {
+            title,
+            id,
+            mdx,
+            db_file,
+            fst,
+            reverse: None,
+            lemmatizer: None,
+            mdds: vec![],
+            dir,
+        })
+    }
+
+    /// load the mdd archives next to the mdx, only needed to serve resources.
+    /// an archive that fails to parse is skipped, the dictionary is still served without it
+    pub fn load_resources(&mut self) {
+        let mut mdd_file = self.dir.join(format!("{}.mdd", self.id));
+        let mut n = 0;
+        while mdd_file.exists() {
+            info!("loading resource file:{}", mdd_file.display());
+            match Mdd::open(&mdd_file.to_string_lossy()) {
+                Ok(mdd) => self.mdds.push(mdd),
+                Err(e) => warn!("skipping resource file {}: {}", mdd_file.display(), e),
+            }
+            n += 1;
+            mdd_file = self.dir.join(format!("{}.{}.mdd", self.id, n));
+        }
+    }
+
+    /// resource bytes from the mdd archives, falling back to a file in the dictionary directory
+    pub fn resource(&self, path: &str) -> Option<Vec<u8>> {
+        for mdd in &self.mdds {
+            if let Some(bytes) = mdd.get(path) {
+                return Some(bytes);
+            }
+        }
+        let rel = Path::new(path.trim_start_matches(|c| c == '/' || c == '\\'));
+        // only plain relative paths, no `..` escaping the dictionary directory
+        if rel.components().any(|c| !matches!(c, Component::Normal(_))) {
+            return None;
+        }
+        std::fs::read(self.dir.join(rel)).ok()
+    }
+
+    /// `resource` on the [`offload`] pool, mdd records are read and inflated like definitions
+    pub async fn resource_async(self: Arc<Self>, path: String) -> Result<Option<Vec<u8>>> {
+        let what = format!("resource {} of {}", path, self.id);
+        offload(&what, move || self.resource(&path)).await
+    }
+
+    /// write the fst index files and use them for lookups from now on
+    pub fn build_fst_index(&mut self) -> Result<()> {
+        self.fst = Some(FstIndex::build(&self.mdx)?);
+        Ok(())
+    }
+
+    /// read every definition into the reverse index, for `/reverse`
+    pub fn build_reverse_index(&mut self, workers: &Workers) {
+        self.reverse = Some(ReverseIndex::build(&self.mdx, workers));
+    }
+
+    /// every record of `word` in entry order, through the fst index, the sqlite index or a scan of
+    /// the records when neither was built or can be read. the fst index compares keys the way the
+    /// dictionary does, ignoring case unless it is KeyCaseSensitive, the other two match the
+    /// headword exactly
+    pub fn find_records(&self, word: &str) -> Vec<RecordIndex> {
+        if let Some(index) = &self.fst {
+            match index.get(word) {
+                Ok(records) => return records,
+                Err(e) => warn!("fst index of {}: {}, trying the next index", self.id, e),
+            }
+        }
+        if Path::new(&self.db_file).exists() {
+            match self.query_index(word) {
+                Ok(records) => return records,
+                Err(e) => warn!("{}: {}, scanning the records", self.db_file, e),
+            }
+        }
+        self.mdx.find(word).into_iter().cloned().collect()
+    }
+
+    // the `entry` column is the position in mdx.records, homographs come back in file order
+    fn query_index(&self, word: &str) -> rusqlite::Result<Vec<RecordIndex>> {
+        let conn = Connection::open(&self.db_file)?;
+        let mut stmt = conn.prepare("select * from MDX_INDEX WHERE key_text= :word order by entry;")?;
+        let rows = stmt.query_map_named(named_params! { ":word": word }, |row| Ok(RecordIndex {
+            key_text: row.get::<usize, String>(0)?,
+            file_pos: row.get::<usize, i64>(1)? as u64,
+            compressed_size: row.get::<usize, u32>(2)? as u32,
+            decompressed_size: row.get::<usize, u32>(3)? as u32,
+            record_block_type: row.get::<usize, u8>(4)? as u32,
+            record_start: row.get::<usize, i64>(5)? as u64,
+            record_end: row.get::<usize, i64>(6)? as u64,
+            offset: row.get::<usize, i64>(7)? as u64,
+        }))?;
+        rows.collect()
+    }
+
+    /// every entry of `word` in file order, `@@@LINK=` records are followed until a real definition
+    /// is reached. a word that is not a headword is looked up by its base forms, the first one found
+    /// is returned. empty when neither is found
+    pub fn lookup(&self, word: &str) -> Vec<Lookup> {
+        let mut found = vec![];
+        self.resolve(word, &[], &mut found);
+        if found.is_empty() {
+            if let Some(lemmatizer) = &self.lemmatizer {
+                for lemma in lemmatizer.lemmas(word) {
+                    self.resolve(&lemma, &[], &mut found);
+                    if !found.is_empty() {
+                        for (_, l) in found.iter_mut() {
+                            l.matched_form = Some(lemma.clone());
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        found.into_iter().map(|(_, l)| l).collect()
+    }
+
+    /// `lookup` on the [`offload`] pool, for async servers
+    pub async fn lookup_async(self: Arc<Self>, word: String) -> Result<Vec<Lookup>> {
+        let what = format!("lookup of {} in {}", word, self.id);
+        offload(&what, move || self.lookup(&word)).await
+    }
+
+    // entries of `headword` with their record_start, a record reached through two links is kept once
+    fn resolve(&self, headword: &str, redirects: &[String], found: &mut Vec<(u64, Lookup)>) {
+        for idx in self.find_records(headword) {
+            if found.iter().any(|(start, _)| *start == idx.record_start) {
+                continue;
+            }
+            let definition = match self.mdx.read_definition(&idx) {
+                Ok(d) => d,
+                Err(e) => {
+                    warn!("{} in {}: {}", idx.key_text, self.id, e);
+                    continue;
+                }
+            };
+            let target = definition.trim_end_matches(|c| c == '\0' || c == '\r' || c == '\n')
+                .strip_prefix(LINK_PREFIX)
+                .map(|t| t.trim().to_string());
+            match target {
+                Some(t) if redirects.len() < MAX_REDIRECTS && t != headword && !redirects.contains(&t) => {
+                    let mut via = redirects.to_vec();
+                    via.push(headword.to_string());
+                    self.resolve(&t, &via, found);
+                }
+                _ => found.push((idx.record_start, Lookup {
+                    headword: idx.key_text,
+                    definition,
+                    redirects: redirects.to_vec(),
+                    matched_form: None,
+                })),
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt;
+use std::io;
+
+/// why an mdx/mdd file could not be parsed, files come from users and may be truncated or hostile
+#[derive(Debug)]
+pub enum MdxError {
+    Io(io::Error),
+    /// `len` bytes at `offset` were needed but the section (or the file) has only `available`
+    Truncated { what: &'static str, offset: u64, len: u64, available: u64 },
+    /// a size field larger than the allocation cap
+    TooLarge { what: &'static str, size: u64, limit: u64 },
+    Checksum(&'static str),
+    Zlib { what: &'static str, message: String },
+    Invalid(String),
+    /// work handed to a pool thread by `offload` panicked, the string says what it was
+    Panicked(String),
+}
+
+pub type Result<T> = std::result::Result<T, MdxError>;
+
+impl fmt::Display for MdxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MdxError::Io(e) => write!(f, "io error: {}", e),
+            MdxError::Truncated { what, offset, len, available } =>
+                write!(f, "{} truncated: needs {} bytes at offset {}, has {}", what, len, offset, available),
+            MdxError::TooLarge { what, size, limit } => write!(f, "{} is {} bytes, limit is {}", what, size, limit),
+            MdxError::Checksum(what) => write!(f, "{} adler32 checksum mismatch", what),
+            MdxError::Zlib { what, message } => write!(f, "{} zlib error: {}", what, message),
+            MdxError::Invalid(message) => write!(f, "{}", message),
+            MdxError::Panicked(what) => write!(f, "{} panicked", what),
+        }
+    }
+}
+
+impl std::error::Error for MdxError {}
+
+impl From<io::Error> for MdxError {
+    fn from(e: io::Error) -> MdxError {
+        MdxError::Io(e)
+    }
+}
+
+/// reject a size read from the file before allocating for it
+pub(crate) fn check_size(size: u64, limit: u64, what: &'static str) -> Result<usize> {
+    if size > limit {
+        return Err(MdxError::TooLarge { what, size, limit });
+    }
+    Ok(size as usize)
+}
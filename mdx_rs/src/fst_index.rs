@@ -0,0 +1,316 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::time::UNIX_EPOCH;
+
+use adler32::RollingAdler32;
+
+use fst::{Automaton, IntoStreamer, Map, MapBuilder, Streamer};
+use fst::automaton::{Levenshtein, Str};
+use memmap::Mmap;
+use regex_automata::dense;
+
+use crate::cursor::Cursor;
+use crate::error::{check_size, MdxError, Result};
+use crate::mdx::{Header, Mdx, RecordIndex};
+use crate::unpack::{Endian, unpack_u32, unpack_u64};
+
+// `<file>.rec`: magic, fingerprint of the mdx it was built from, record count and key flags (u32),
+// then the records sorted by normalized key and the key texts they point into. little endian
+const MAGIC: &[u8; 8] = b"MDXREC03";
+// length and modification time in nanoseconds (u64), adler32 of the header section (u32)
+const FINGERPRINT_SIZE: usize = 20;
+const HEADER_SIZE: usize = 8 + FINGERPRINT_SIZE + 8;
+// the header section is read to fingerprint the mdx, same bound as when parsing it
+const MAX_HEADER_SIZE: u64 = 8 << 20;
+// entry, the seven numbers of a RecordIndex, key text offset and length, all u64
+const RECORD_SIZE: usize = 80;
+const CASE_SENSITIVE: u32 = 1;
+const STRIP_KEY: u32 = 2;
+
+/// compact read-only index of an mdx, the alternative to the sqlite MDX_INDEX. `<file>.fst` maps
+/// each normalized headword to a run of records in `<file>.rec`, both files are memory-mapped.
+/// answers exact, prefix, range, regex and levenshtein queries, results come in key order and
+/// homographs in file order.
+///
+/// keys are normalized the way MDict compares them: lowercased unless the dictionary is
+/// KeyCaseSensitive, spaces and punctuation removed when it is StripKey. the sqlite index and
+/// `Mdx::find` match `key_text` exactly, so `Apple` finds `apple` only through this index
+pub struct FstIndex {
+    map: Map<Mmap>,
+    records: Mmap,
+    count: usize,
+    flags: u32,
+}
+
+impl FstIndex {
+    /// `<file>.fst` and `<file>.rec`, next to the mdx like its `.db`
+    pub fn paths(mdx_file: &str) -> (String, String) {
+        (format!("{}.fst", mdx_file), format!("{}.rec", mdx_file))
+    }
+
+    /// map the index files of `mdx_file`, an error when they are missing, corrupt or were built
+    /// from a different version of the mdx: its length, modification time or header changed
+    pub fn open(mdx_file: &str) -> Result<FstIndex> {
+        let (fst_file, rec_file) = FstIndex::paths(mdx_file);
+        let records = map_file(&rec_file)?;
+        let mut header = Cursor::new(&records[..]);
+        if header.take(MAGIC.len(), "index header")? != MAGIC {
+            return Err(MdxError::Invalid(format!("{} is not an mdx record file", rec_file)));
+        }
+        let built_from = header.take(FINGERPRINT_SIZE, "index header")?;
+        let count = unpack_u32(header.take(4, "index header")?, Endian::LE) as usize;
+        let flags = unpack_u32(header.take(4, "index header")?, Endian::LE);
+        if built_from != &fingerprint(mdx_file)?[..] {
+            return Err(MdxError::Invalid(format!("{} was built from a different version of {}", rec_file, mdx_file)));
+        }
+        header.skip(count.saturating_mul(RECORD_SIZE), "index records")?;
+        let map = Map::new(map_file(&fst_file)?).map_err(fst_error)?;
+        Ok(FstIndex { map, records, count, flags })
+    }
+
+    /// write the index files of an mdx and open them. the files are written under a temporary
+    /// name and renamed, a server reading the old index never sees a half-written one
+    pub fn build(mdx: &Mdx) -> Result<FstIndex> {
+        let flags = key_flags(&mdx.header);
+        let mut order: Vec<(String, usize)> = mdx.records.iter().enumerate()
+            .map(|(entry, r)| (normalize(&r.key_text, flags), entry))
+            .collect();
+        // by key, then entry: the fst needs its keys sorted, homographs stay in file order
+        order.sort();
+
+        let (fst_file, rec_file) = FstIndex::paths(&mdx.filename);
+        let rec_tmp = format!("{}.tmp", rec_file);
+        let mut rec = BufWriter::new(File::create(&rec_tmp)?);
+        rec.write_all(MAGIC)?;
+        rec.write_all(&fingerprint(&mdx.filename)?)?;
+        rec.write_all(&(order.len() as u32).to_le_bytes())?;
+        rec.write_all(&flags.to_le_bytes())?;
+        let mut key_offset = 0;
+        for (_, entry) in &order {
+            let r = &mdx.records[*entry];
+            let key_len = r.key_text.len() as u64;
+            let fields = [*entry as u64, r.file_pos, r.compressed_size as u64, r.decompressed_size as u64,
+                r.record_block_type as u64, r.record_start, r.record_end, r.offset, key_offset, key_len];
+            for n in fields.iter() {
+                rec.write_all(&n.to_le_bytes())?;
+            }
+            key_offset += key_len;
+        }
+        for (_, entry) in &order {
+            rec.write_all(mdx.records[*entry].key_text.as_bytes())?;
+        }
+        rec.flush()?;
+
+        // value: index of the first record of the key in the high 32 bits, number of records in the low
+        let fst_tmp = format!("{}.tmp", fst_file);
+        let mut map = MapBuilder::new(BufWriter::new(File::create(&fst_tmp)?)).map_err(fst_error)?;
+        let mut start = 0;
+        while start < order.len() {
+            let key = &order[start].0;
+            let n = order[start..].iter().take_while(|(k, _)| k == key).count();
+            map.insert(key, (start as u64) << 32 | n as u64).map_err(fst_error)?;
+            start += n;
+        }
+        map.finish().map_err(fst_error)?;
+
+        fs::rename(&rec_tmp, &rec_file)?;
+        fs::rename(&fst_tmp, &fst_file)?;
+        FstIndex::open(&mdx.filename)
+    }
+
+    /// number of records, entries of the mdx
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// records of `word`, compared the way the dictionary compares keys: ignoring case unless it is
+    /// KeyCaseSensitive, ignoring spaces and punctuation when it is StripKey. unlike the exact
+    /// match of the sqlite index
+    pub fn get(&self, word: &str) -> Result<Vec<RecordIndex>> {
+        match self.map.get(normalize(word, self.flags)) {
+            Some(value) => self.run(value).collect(),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// records whose key starts with `prefix`, at most `limit`
+    pub fn prefix(&self, prefix: &str, limit: usize) -> Result<Vec<RecordIndex>> {
+        let prefix = normalize(prefix, self.flags);
+        self.collect(self.map.search(Str::new(&prefix).starts_with()).into_stream(), limit)
+    }
+
+    /// records with keys from `from` up to but not including `to`, at most `limit`
+    pub fn range(&self, from: &str, to: &str, limit: usize) -> Result<Vec<RecordIndex>> {
+        let (from, to) = (normalize(from, self.flags), normalize(to, self.flags));
+        self.collect(self.map.range().ge(from).lt(to).into_stream(), limit)
+    }
+
+    /// records whose whole normalized key matches `pattern`, at most `limit`. case is ignored
+    /// unless the dictionary is KeyCaseSensitive
+    pub fn regex(&self, pattern: &str, limit: usize) -> Result<Vec<RecordIndex>> {
+        let dfa = dense::Builder::new()
+            .anchored(true)
+            .case_insensitive(self.flags & CASE_SENSITIVE == 0)
+            .build(pattern)
+            .map_err(|e| MdxError::Invalid(format!("regex {:?}: {}", pattern, e)))?;
+        self.collect(self.map.search(dfa).into_stream(), limit)
+    }
+
+    /// records whose key is within `distance` edits of `word`, at most `limit`
+    pub fn fuzzy(&self, word: &str, distance: u32, limit: usize) -> Result<Vec<RecordIndex>> {
+        let lev = Levenshtein::new(&normalize(word, self.flags), distance)
+            .map_err(|e| MdxError::Invalid(format!("fuzzy {:?}: {}", word, e)))?;
+        self.collect(self.map.search(lev).into_stream(), limit)
+    }
+
+    fn collect<S>(&self, mut stream: S, limit: usize) -> Result<Vec<RecordIndex>>
+        where S: for<'a> Streamer<'a, Item=(&'a [u8], u64)> {
+        let mut records = vec![];
+        while let Some((_, value)) = stream.next() {
+            for r in self.run(value) {
+                if records.len() >= limit {
+                    return Ok(records);
+                }
+                records.push(r?);
+            }
+        }
+        Ok(records)
+    }
+
+    // the records of one fst value
+    fn run(&self, value: u64) -> impl Iterator<Item=Result<RecordIndex>> + '_ {
+        let start = (value >> 32) as usize;
+        let n = (value & 0xffff_ffff) as usize;
+        (start..start + n).map(move |i| self.record(i))
+    }
+
+    fn record(&self, i: usize) -> Result<RecordIndex> {
+        if i >= self.count {
+            return Err(MdxError::Invalid(format!("index record {} of {}", i, self.count)));
+        }
+        let mut input = Cursor::new(&self.records[..]);
+        input.skip(HEADER_SIZE + i * RECORD_SIZE, "index records")?;
+        let mut fields = [0u64; 10];
+        for n in fields.iter_mut() {
+            *n = unpack_u64(input.take(8, "index record")?, Endian::LE);
+        }
+        let mut keys = Cursor::new(&self.records[..]);
+        keys.skip(HEADER_SIZE + self.count * RECORD_SIZE + fields[8] as usize, "index keys")?;
+        let key_text = String::from_utf8(keys.take(fields[9] as usize, "index key")?.to_vec())
+            .map_err(|e| MdxError::Invalid(format!("index key {}: {}", i, e)))?;
+        Ok(RecordIndex {
+            key_text,
+            file_pos: fields[1],
+            compressed_size: fields[2] as u32,
+            decompressed_size: fields[3] as u32,
+            record_block_type: fields[4] as u32,
+            record_start: fields[5],
+            record_end: fields[6],
+            offset: fields[7],
+        })
+    }
+}
+
+// what `open` compares to tell whether the mdx changed since the index was built. length and mtime
+// are cheap, the header checksum catches a copy with a fresh mtime of a different dictionary
+fn fingerprint(mdx_file: &str) -> Result<[u8; FINGERPRINT_SIZE]> {
+    let meta = fs::metadata(mdx_file)?;
+    let mtime = meta.modified()?.duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+    let mut file = Cursor::with_len(File::open(mdx_file)?, meta.len());
+    // u32 big endian length, the utf-16 text and its adler32
+    let header_len = file.u32(Endian::BE, "header length")? as u64;
+    check_size(header_len, MAX_HEADER_SIZE, "header")?;
+    let header = file.read(header_len + 4, "header")?;
+    let mut print = [0; FINGERPRINT_SIZE];
+    print[..8].copy_from_slice(&meta.len().to_le_bytes());
+    print[8..16].copy_from_slice(&mtime.to_le_bytes());
+    print[16..].copy_from_slice(&RollingAdler32::from_buffer(&header).hash().to_le_bytes());
+    Ok(print)
+}
+
+fn key_flags(header: &Header) -> u32 {
+    let mut flags = 0;
+    if header.keycasesensitive {
+        flags |= CASE_SENSITIVE;
+    }
+    if header.stripkey {
+        flags |= STRIP_KEY;
+    }
+    flags
+}
+
+// key as stored in the fst
+fn normalize(key: &str, flags: u32) -> String {
+    let key = key.trim();
+    let key: String = if flags & STRIP_KEY != 0 {
+        key.chars().filter(|c| !c.is_whitespace() && !c.is_ascii_punctuation()).collect()
+    } else {
+        key.to_string()
+    };
+    if flags & CASE_SENSITIVE != 0 { key } else { key.to_lowercase() }
+}
+
+fn map_file(path: &str) -> Result<Mmap> {
+    let file = File::open(path)?;
+    // the index files are only ever replaced by rename, never written in place
+    Ok(unsafe { Mmap::map(&file)? })
+}
+
+fn fst_error(e: fst::Error) -> MdxError {
+    MdxError::Invalid(format!("fst index: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    use super::FstIndex;
+    use crate::mdx::{Mdx, RecordIndex};
+
+    const MDX: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../resources/葛传椝英语惯用法词典.mdx");
+
+    fn keys(records: Vec<RecordIndex>) -> Vec<String> {
+        records.into_iter().map(|r| r.key_text).collect()
+    }
+
+    #[test]
+    fn build_open_and_query() {
+        // a copy, the index files are written next to the mdx
+        let dir = std::env::temp_dir().join(format!("mdx_rs_fst_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("dict.mdx");
+        fs::copy(MDX, &file).unwrap();
+        let file = file.to_str().unwrap();
+
+        let mdx = Mdx::open(file).unwrap();
+        let built = FstIndex::build(&mdx).unwrap();
+        assert_eq!(built.len(), mdx.records.len());
+        let index = FstIndex::open(file).unwrap();
+
+        // the dictionary is not KeyCaseSensitive
+        assert_eq!(keys(index.get("ABC").unwrap()), ["ABC"]);
+        assert_eq!(keys(index.get("abc").unwrap()), ["ABC"]);
+        assert!(index.get("abcd").unwrap().is_empty());
+        let record = &index.get("about").unwrap()[0];
+        assert_eq!(mdx.read_definition(record).unwrap(), mdx.lookup("about").unwrap()[0]);
+
+        assert_eq!(keys(index.prefix("abs", 10).unwrap()), ["absence", "absent", "absorbed", "abstain"]);
+        assert_eq!(keys(index.prefix("abs", 2).unwrap()), ["absence", "absent"]);
+        assert_eq!(keys(index.range("abo", "abr", 10).unwrap()), ["aboard", "abound", "about", "above"]);
+        assert_eq!(keys(index.regex("ab.ut", 10).unwrap()), ["about"]);
+        assert_eq!(keys(index.regex("AB[CD]", 10).unwrap()), ["ABC"]);
+        assert!(index.regex("(", 10).is_err());
+        assert!(keys(index.fuzzy("abuot", 2, 10).unwrap()).contains(&"about".to_string()));
+        assert!(!keys(index.fuzzy("abuot", 1, 10).unwrap()).contains(&"about".to_string()));
+
+        // a changed mdx makes the index stale
+        OpenOptions::new().append(true).open(file).unwrap().write_all(b"\0").unwrap();
+        assert!(FstIndex::open(file).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
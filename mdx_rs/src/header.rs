@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use log::warn;
+
+use crate::render::decode_entities;
+
+/// attributes of the `<Dictionary .../>` (mdx) or `<Library_Data .../>` (mdd) header element,
+/// values are unescaped. both quote styles and whitespace around `=` are accepted, an attribute
+/// without a value maps to "" and parsing stops at the end of the start tag
+pub fn parse_attributes(txt: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let start = match txt.find('<') {
+        Some(i) => i,
+        None => {
+            warn!("header has no element: {:?}", txt.chars().take(40).collect::<String>());
+            return attrs;
+        }
+    };
+    let mut chars = txt[start + 1..].char_indices().peekable();
+    let element = take_name(txt, start + 1, &mut chars);
+    if element != "Dictionary" && element != "Library_Data" {
+        warn!("unexpected header element <{}>", element);
+    }
+    loop {
+        skip_whitespace(&mut chars);
+        match chars.peek() {
+            None | Some((_, '>')) | Some((_, '/')) => break,
+            _ => {}
+        }
+        let name = take_name(txt, start + 1, &mut chars);
+        if name.is_empty() {
+            // stray character, e.g. a quote without a name before it
+            chars.next();
+            continue;
+        }
+        skip_whitespace(&mut chars);
+        if let Some((_, '=')) = chars.peek() {
+            chars.next();
+            skip_whitespace(&mut chars);
+            let value = take_value(txt, start + 1, &mut chars);
+            attrs.insert(name, decode_entities(&value));
+        } else {
+            attrs.insert(name, String::new());
+        }
+    }
+    attrs
+}
+
+fn skip_whitespace(chars: &mut Peekable<CharIndices<'_>>) {
+    while let Some((_, c)) = chars.peek() {
+        if !c.is_whitespace() {
+            break;
+        }
+        chars.next();
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.' || c == ':'
+}
+
+// `base` is the offset of the iterated slice in txt
+fn take_name(txt: &str, base: usize, chars: &mut Peekable<CharIndices<'_>>) -> String {
+    let from = match chars.peek() {
+        Some((i, _)) => base + i,
+        None => return String::new(),
+    };
+    let mut to = from;
+    while let Some((i, c)) = chars.peek() {
+        if !is_name_char(*c) {
+            break;
+        }
+        to = base + i + c.len_utf8();
+        chars.next();
+    }
+    txt[from..to].to_string()
+}
+
+// quoted value runs to the matching quote, a missing closing quote takes the rest of the text.
+// unquoted values (not valid xml, written by some tools) end at whitespace or the end of the tag
+fn take_value(txt: &str, base: usize, chars: &mut Peekable<CharIndices<'_>>) -> String {
+    let quote = match chars.peek() {
+        Some((_, q)) if *q == '"' || *q == '\'' => Some(*q),
+        Some(_) => None,
+        None => return String::new(),
+    };
+    if quote.is_some() {
+        chars.next();
+    }
+    let from = match chars.peek() {
+        Some((i, _)) => base + i,
+        None => return String::new(),
+    };
+    let mut to = txt.len();
+    while let Some((i, c)) = chars.next() {
+        let end = match quote {
+            Some(q) => c == q,
+            None => c.is_whitespace() || c == '>' || (c == '/' && matches!(chars.peek(), Some((_, '>')))),
+        };
+        if end {
+            to = base + i;
+            break;
+        }
+    }
+    txt[from..to].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_attributes;
+
+    // header of 朗文当代双解, the sample in src/bin/byteorder.rs of the web app
+    const LONGMAN: &str = r##"<Dictionary GeneratedByEngineVersion="2.0" RequiredEngineVersion="2.0" Format="Html" KeyCaseSensitive="No" StripKey="Yes" Encrypted="2"
+    RegisterBy="EMail"
+    Description="&lt;table width=&quot;100%&quot; border=&quot;0&quot; bgcolor=&quot;#fef4f4&quot;&gt;&lt;tr&gt;&lt;td align=&quot;center&quot;&gt;&lt;h1 style=&quot;color:deeppink; font-family: Microsoft Yahei, sans-serif; margin-top: 5pt&quot;&gt;朗文当代高级英语辞典（第四版）&lt;/h1&gt;&lt;/td&gt;&lt;/tr&gt;&lt;/table&gt;"
+    Title="朗文当代双解"
+    Encoding="UTF-8"
+    CreationDate="2015-7-18"
+    Compact="Yes"
+    Compat="Yes"
+    Left2Right="Yes"
+    DataSourceFormat="106"
+    StyleSheet=""/>"##;
+
+    #[test]
+    fn longman_header() {
+        let attrs = parse_attributes(LONGMAN);
+        assert_eq!(attrs.len(), 16);
+        assert_eq!(attrs["GeneratedByEngineVersion"], "2.0");
+        assert_eq!(attrs["Encrypted"], "2");
+        assert_eq!(attrs["Title"], "朗文当代双解");
+        assert_eq!(attrs["CreationDate"], "2015-7-18");
+        assert_eq!(attrs["StyleSheet"], "");
+        assert!(attrs["Description"].starts_with(r##"<table width="100%" border="0" bgcolor="#fef4f4"><tr>"##));
+        assert!(attrs["Description"].ends_with("</h1></td></tr></table>"));
+    }
+
+    #[test]
+    fn single_quotes_and_spaces_around_eq() {
+        let attrs = parse_attributes(r#"<Dictionary Title = 'Tom"s dictionary' Format= "Html" Encoding ='UTF-8'/>"#);
+        assert_eq!(attrs["Title"], r#"Tom"s dictionary"#);
+        assert_eq!(attrs["Format"], "Html");
+        assert_eq!(attrs["Encoding"], "UTF-8");
+    }
+
+    #[test]
+    fn escaped_quotes() {
+        let attrs = parse_attributes(r#"<Dictionary Title="say &quot;hi&quot;" Description='it&apos;s &amp; more' Note="&#39;&#x41;"/>"#);
+        assert_eq!(attrs["Title"], r#"say "hi""#);
+        assert_eq!(attrs["Description"], "it's & more");
+        assert_eq!(attrs["Note"], "'A");
+    }
+
+    #[test]
+    fn multi_line_stylesheet() {
+        let attrs = parse_attributes("<Dictionary StyleSheet=\"1\r\n&lt;b&gt;\r\n&lt;/b&gt;\r\n\" Title=\"t\"/>\r\n\0");
+        assert_eq!(attrs["StyleSheet"], "1\r\n<b>\r\n</b>\r\n");
+        assert_eq!(attrs["Title"], "t");
+    }
+
+    #[test]
+    fn library_data_unquoted_and_empty() {
+        let attrs = parse_attributes(r#"<Library_Data GeneratedByEngineVersion=2.0 Compact Encrypted="0">"#);
+        assert_eq!(attrs["GeneratedByEngineVersion"], "2.0");
+        assert_eq!(attrs["Compact"], "");
+        assert_eq!(attrs["Encrypted"], "0");
+    }
+
+    #[test]
+    fn attributes_after_the_tag_are_ignored() {
+        let attrs = parse_attributes(r#"<Dictionary Title="a"/> Format="Text""#);
+        assert_eq!(attrs.len(), 1);
+        assert!(!attrs.contains_key("Format"));
+    }
+
+    #[test]
+    fn unterminated_value_and_garbage() {
+        assert!(parse_attributes("no element here").is_empty());
+        let attrs = parse_attributes(r#"<Dictionary Title="never closed"#);
+        assert_eq!(attrs["Title"], "never closed");
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::fs;
+
+use regex::Regex;
+
+use crate::error::{MdxError, Result};
+
+// inflected form and its base forms, most likely first, comma separated
+const IRREGULAR: &str = "\
+am be, are be, is be, was be, were be, been be, being be, has have, had have, having have, does do, did do,
+done do, goes go, went go, gone go, ate eat, eaten eat, began begin, begun begin, bent bend, bit bite,
+bitten bite, blew blow, blown blow, broke break, broken break, brought bring, built build, bought buy,
+caught catch, chose choose, chosen choose, came come, dealt deal, drew draw, drawn draw, drank drink,
+drunk drink, drove drive, driven drive, fell fall, fallen fall, fed feed, felt feel, fought fight,
+found find, fled flee, flew fly, flown fly, forgot forget, forgotten forget, forgave forgive,
+forgiven forgive, froze freeze, frozen freeze, got get, gotten get, gave give, given give, grew grow,
+grown grow, hung hang, heard hear, hid hide, hidden hide, held hold, kept keep, knew know, known know,
+laid lay, led lead, left leave, lent lend, lay lie, lain lie, lost lose, made make, meant mean, met meet,
+paid pay, rode ride, ridden ride, rang ring, rung ring, rose rise, risen rise, ran run, said say, saw see,
+seen see, sought seek, sold sell, sent send, shook shake, shaken shake, shone shine, shot shoot, shown show,
+sang sing, sung sing, sank sink, sunk sink, sat sit, slept sleep, slid slide, spoke speak, spoken speak,
+spent spend, spun spin, stood stand, stole steal, stolen steal, stuck stick, stung sting, struck strike,
+swore swear, sworn swear, swam swim, swum swim, swung swing, took take, taken take, taught teach, tore tear,
+torn tear, told tell, thought think, threw throw, thrown throw, understood understand, woke wake, woken wake,
+wore wear, worn wear, won win, wound wind, wrote write, written write, bore bear, borne bear, born bear,
+lit light, slain slay, sprang spring, sprung spring, men man, women woman, children child, feet foot,
+teeth tooth, geese goose, mice mouse, lice louse, oxen ox, people person, dice die, criteria criterion,
+phenomena phenomenon, data datum, media medium, analyses analysis, crises crisis, theses thesis,
+cacti cactus, fungi fungus, nuclei nucleus, radii radius, stimuli stimulus, indices index,
+appendices appendix, matrices matrix, better good well, best good well, worse bad ill, worst bad ill,
+more much many, most much many, less little, least little, further far, furthest far, farther far,
+farthest far, elder old, eldest old";
+
+// english inflections in hunspell SFX syntax: flag, stripped from the base, added, condition on the base
+const SUFFIXES: &str = "
+SFX S y ies [^aeiou]y
+SFX S 0 es [sxz]
+SFX S 0 es [cs]h
+SFX S 0 s [^sxzhy]
+SFX S 0 s [aeiou]y
+SFX S 0 s [^cs]h
+SFX V f ves [^f]f
+SFX V fe ves fe
+SFX D 0 d e
+SFX D y ied [^aeiou]y
+SFX D 0 ed [^ey]
+SFX D 0 ed [aeiou]y
+SFX G e ing [^e]e
+SFX G 0 ing [^e]
+SFX G ie ying ie
+SFX R 0 r e
+SFX R y ier [^aeiou]y
+SFX R 0 er [^ey]
+SFX R 0 er [aeiou]y
+SFX T 0 st e
+SFX T y iest [^aeiou]y
+SFX T 0 est [^ey]
+SFX T 0 est [aeiou]y
+SFX Y 0 ly [^y]
+SFX Y y ily [^aeiou]y
+SFX Y le ly [^aeiou]le
+";
+
+// running, stopped, bigger: the final consonant of a short stem is doubled before these
+const DOUBLED: &str = "bdglmnprt";
+const DOUBLING_SUFFIXES: [&str; 4] = ["ing", "ed", "er", "est"];
+
+/// one hunspell suffix rule read backwards: a word ending in `add` may come from the base that
+/// ends in `strip` instead, when the base matches `condition`
+#[derive(Clone)]
+struct Suffix {
+    strip: String,
+    add: String,
+    condition: Regex,
+}
+
+/// base forms of english words for lookups of inflected forms: an irregular forms table
+/// (`geese`, `went`, `better`) and suffix rules, built in or read from a hunspell `.aff` file
+#[derive(Clone)]
+pub struct Lemmatizer {
+    irregular: HashMap<String, Vec<String>>,
+    suffixes: Vec<Suffix>,
+}
+
+impl Lemmatizer {
+    /// the built-in english table and rules
+    pub fn english() -> Lemmatizer {
+        let mut irregular = HashMap::new();
+        for entry in IRREGULAR.split(',') {
+            let mut words = entry.split_whitespace();
+            if let Some(form) = words.next() {
+                irregular.entry(form.to_string()).or_insert_with(Vec::new).extend(words.map(|w| w.to_string()));
+            }
+        }
+        let mut suffixes = parse_affix(SUFFIXES).expect("built-in suffix rules");
+        for c in DOUBLED.chars() {
+            for suffix in DOUBLING_SUFFIXES.iter() {
+                let rule = format!("SFX X 0 {}{} [^aeiou][aeiou]{}", c, suffix, c);
+                suffixes.extend(parse_affix(&rule).expect("built-in suffix rules"));
+            }
+        }
+        Lemmatizer { irregular, suffixes }
+    }
+
+    /// the built-in rules followed by the SFX rules of a hunspell affix file, e.g. `en_US.aff`
+    pub fn with_affix_file(path: &str) -> Result<Lemmatizer> {
+        let text = fs::read_to_string(path)?;
+        let mut lemmatizer = Lemmatizer::english();
+        lemmatizer.suffixes.extend(parse_affix(&text)?);
+        Ok(lemmatizer)
+    }
+
+    /// possible base forms of `word`, most likely first, without `word` itself. the lowercased
+    /// word comes first, then irregular forms, then the suffix rules in file order
+    pub fn lemmas(&self, word: &str) -> Vec<String> {
+        let word = word.trim();
+        let lower = word.to_lowercase();
+        let mut lemmas = vec![lower.clone()];
+        if let Some(bases) = self.irregular.get(&lower) {
+            lemmas.extend(bases.iter().cloned());
+        }
+        for s in &self.suffixes {
+            if s.add.is_empty() || lower.len() <= s.add.len() || !lower.ends_with(&s.add) {
+                continue;
+            }
+            let base = format!("{}{}", &lower[..lower.len() - s.add.len()], s.strip);
+            if s.condition.is_match(&base) {
+                lemmas.push(base);
+            }
+        }
+        let mut seen = vec![word.to_string()];
+        lemmas.retain(|l| {
+            let new = !seen.contains(l);
+            seen.push(l.clone());
+            new
+        });
+        lemmas
+    }
+}
+
+// `SFX flag strip add condition` lines, `SFX flag Y count` headers and everything else skipped.
+// `0` is the empty string, `/flags` after the added text are continuation classes and ignored
+fn parse_affix(text: &str) -> Result<Vec<Suffix>> {
+    let mut suffixes = vec![];
+    for (n, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 5 || fields[0] != "SFX" {
+            continue;
+        }
+        let empty = |s: &str| if s == "0" { String::new() } else { s.to_lowercase() };
+        let add = fields[3].split('/').next().unwrap_or("");
+        let condition = match fields[4] {
+            "." => String::new(),
+            c => c.to_string(),
+        };
+        let condition = Regex::new(&format!("{}$", condition))
+            .map_err(|e| MdxError::Invalid(format!("affix line {}: condition {}: {}", n + 1, fields[4], e)))?;
+        suffixes.push(Suffix { strip: empty(fields[2]), add: empty(add), condition });
+    }
+    Ok(suffixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lemmatizer, parse_affix};
+
+    #[test]
+    fn irregular_forms() {
+        let english = Lemmatizer::english();
+        assert_eq!(english.lemmas("went"), vec!["go"]);
+        assert_eq!(english.lemmas("geese"), vec!["goose"]);
+        assert_eq!(english.lemmas("better")[..2], ["good", "well"]);
+    }
+
+    #[test]
+    fn doubled_consonants() {
+        let english = Lemmatizer::english();
+        assert!(english.lemmas("running").contains(&"run".to_string()));
+        assert!(english.lemmas("stopped").contains(&"stop".to_string()));
+        assert!(english.lemmas("bigger").contains(&"big".to_string()));
+        // only a consonant after a single vowel is doubled
+        assert!(!english.lemmas("feeding").contains(&"fee".to_string()));
+    }
+
+    #[test]
+    fn ies_and_ves() {
+        let english = Lemmatizer::english();
+        assert_eq!(english.lemmas("cities")[0], "city");
+        assert_eq!(english.lemmas("studies")[0], "study");
+        assert!(english.lemmas("wolves").contains(&"wolf".to_string()));
+        assert!(english.lemmas("knives").contains(&"knife".to_string()));
+    }
+
+    #[test]
+    fn lowercase_then_irregular_then_rules() {
+        let english = Lemmatizer::english();
+        let lemmas = english.lemmas("Was");
+        assert_eq!(lemmas[..3], ["was", "be", "wa"]);
+        assert_eq!(english.lemmas("Went"), vec!["went", "go"]);
+        // no duplicates and never the word itself
+        let lemmas = english.lemmas("boxes");
+        assert!(!lemmas.contains(&"boxes".to_string()));
+        assert!(lemmas.iter().enumerate().all(|(i, l)| !lemmas[..i].contains(l)));
+        assert!(english.lemmas("apple").is_empty());
+    }
+
+    #[test]
+    fn affix_rules() {
+        let aff = "SET UTF-8\nTRY esianrtolcdugmphbyfvkwz\n\nSFX N Y 2\nSFX N e ion/S e\nSFX N 0 ation [^e]\n\
+                   SFX L Y 1\nSFX L 0 ly .\nPFX A Y 1\nPFX A 0 re .\n";
+        let suffixes = parse_affix(aff).unwrap();
+        let rules: Vec<(&str, &str)> = suffixes.iter().map(|s| (s.strip.as_str(), s.add.as_str())).collect();
+        assert_eq!(rules, vec![("e", "ion"), ("", "ation"), ("", "ly")]);
+        assert!(suffixes[0].condition.is_match("create"));
+        assert!(!suffixes[1].condition.is_match("create"));
+        assert!(suffixes[2].condition.is_match("quick"));
+        assert!(parse_affix("SFX B 0 able [^e\n").is_err());
+    }
+
+    #[test]
+    fn affix_rules_extend_the_english_ones() {
+        let mut english = Lemmatizer::english();
+        assert!(!english.lemmas("creation").contains(&"create".to_string()));
+        english.suffixes.extend(parse_affix("SFX N e ion e\n").unwrap());
+        assert!(english.lemmas("creation").contains(&"create".to_string()));
+    }
+}
@@ -0,0 +1,67 @@
+//! reader for MDict dictionaries: `.mdx` files (headwords and html definitions) and the `.mdd`
+//! resource archives shipped with them.
+//!
+//! - [`Mdx`] parses a file, lists its keys and reads records, [`Mdx::entries`] streams every entry
+//! - [`Mdd`] serves images, css and audio by path
+//! - [`Dict`] is an mdx with its mdd archives and sqlite index, [`Dict::lookup`] follows `@@@LINK=` redirects
+//!   and tries the base forms from a [`Lemmatizer`] when a word is not a headword, [`Dict::lookup_async`]
+//!   runs it on the [`offload`] pool for async servers
+//! - [`FstIndex`] is a compact memory-mapped index with prefix, range, regex and fuzzy queries
+//! - [`KeyPattern`] matches headwords by substring, wildcards or regex for [`Mdx::search_keys`]
+//! - [`ReverseIndex`] finds the headwords whose definitions mention a word, cjk text included
+//! - [`anki::export`] writes looked up words and their media as an Anki deck
+//! - [`Header`] holds the header attributes, every parse error is an [`MdxError`]
+//!
+//! ```no_run
+//! use mdx_rs::{Mdx, Result};
+//!
+//! fn main() -> Result<()> {
+//!     let mdx = Mdx::open("LSC4.mdx")?;
+//!     println!("{}: {} entries", mdx.header.title, mdx.num_entries);
+//!     for definition in mdx.lookup("apple")? {
+//!         println!("{}", definition);
+//!     }
+//!     for (headword, definition) in mdx.entries().take(3) {
+//!         println!("{}\n{}\n", headword, definition);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! the `mdx` command line tool, the `mdx-server` http server and the fuzz targets in `fuzz/`
+//! are built on this crate
+
+pub mod anki;
+mod checksum;
+mod cursor;
+mod dict;
+mod error;
+mod fst_index;
+mod header;
+mod lemma;
+mod mdd;
+mod mdx;
+pub mod render;
+mod reverse;
+mod search;
+mod unpack;
+mod verify;
+mod workers;
+
+pub use crate::dict::{Dict, Lookup};
+pub use crate::error::{MdxError, Result};
+pub use crate::fst_index::FstIndex;
+pub use crate::lemma::Lemmatizer;
+pub use crate::mdd::Mdd;
+pub use crate::mdx::{Entries, Header, KeyIndex, Mdx, RecordIndex, Stats};
+pub use crate::reverse::ReverseIndex;
+pub use crate::search::{KeyPage, KeyPattern};
+pub use crate::verify::{Problem, Section, VerifyReport};
+pub use crate::workers::{offload, Workers};
+
+/// the block decoders the targets in `fuzz/` feed with arbitrary bytes, not a stable api
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing {
+    pub use crate::mdx::{decode_key_block_bytes, decode_key_block_info, decompress_record_block_bytes, HeaderBuilder,
+                         KeyBlockInfo, parse_header};
+}
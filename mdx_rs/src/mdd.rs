@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use log::warn;
+
+use crate::error::Result;
+use crate::mdx::Mdx;
+
+/// mdd is the resource archive shipped with an mdx: images, css, js and audio.
+/// it has the same layout as mdx, keys are paths like `\img\a.png` and records are raw file bytes
+pub struct Mdd {
+    pub mdx: Mdx,
+    // normalized key -> position in mdx.records
+    index: HashMap<String, usize>,
+}
+
+impl Mdd {
+    pub fn open(file: &str) -> Result<Mdd> {
+        let mdx = Mdx::open(file)?;
+        let mut index = HashMap::with_capacity(mdx.records.len());
+        for (i, r) in mdx.records.iter().enumerate() {
+            index.entry(Mdd::normalize(&r.key_text)).or_insert(i);
+        }
+        Ok(Mdd { mdx, index })
+    }
+
+    /// `img/a.png`, `/img/a.png` and `\IMG\a.png` all map to `\img\a.png`
+    pub fn normalize(path: &str) -> String {
+        let mut key = path.replace('/', "\\").to_lowercase();
+        if !key.starts_with('\\') {
+            key.insert(0, '\\');
+        }
+        key
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.index.contains_key(&Mdd::normalize(path))
+    }
+
+    /// read the resource bytes, None if the path is not in this archive or can not be read
+    pub fn get(&self, path: &str) -> Option<Vec<u8>> {
+        let idx = &self.mdx.records[*self.index.get(&Mdd::normalize(path))?];
+        match self.mdx.read_record(idx) {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                warn!("{} in {}: {}", path, self.mdx.filename, e);
+                None
+            }
+        }
+    }
+}
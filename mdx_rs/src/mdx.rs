@@ -1,34 +1,68 @@
 extern crate ripemd128;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fs::File;
-use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::io::{BufReader, Read, Seek, SeekFrom};
 
-use flate2::write::ZlibDecoder;
+use flate2::read::ZlibDecoder;
+use lazy_static::lazy_static;
+use log::{debug, warn};
 use regex::Regex;
 use ripemd128::{Digest, Ripemd128};
+use serde_derive::Serialize;
 
 use crate::checksum::adler32_checksum;
-use crate::number::{NumberBytes, read_number};
-use crate::unpack::{Endian, unpack_u16, unpack_u32, unpack_u64, utf16_le_string};
+use crate::cursor::Cursor;
+use crate::error::{check_size, MdxError, Result};
+use crate::header::parse_attributes;
+use crate::workers::Workers;
+use crate::unpack::{Endian, utf16_le_string};
 
+// caps on sizes read from the file. dictionaries are uploaded by users, a few wrong bytes must not
+// make the server allocate gigabytes. compressed sections are also bounded by the file length
+const MAX_HEADER_SIZE: u64 = 8 << 20;
+// one decompressed block: key block info, a key block or a record block
+pub(crate) const MAX_BLOCK_SIZE: usize = 64 << 20;
+// all decompressed key blocks together, they are held in memory while the keys are split
+const MAX_KEY_BLOCKS_SIZE: u64 = 1 << 30;
 
-#[derive(Debug)]
+lazy_static! {
+    // `` `n` `` style markers in definitions
+    static ref STYLE_MARKER: Regex = Regex::new(r"`(\d+)`").unwrap();
+}
+
+/// where the record of one key is. positions are u64, mdd files of several GiB are common,
+/// block sizes are bounded by MAX_BLOCK_SIZE
+#[derive(Debug, Clone)]
 pub struct RecordIndex {
     pub key_text: String,
-    pub file_pos: u32,
+    pub file_pos: u64,
     pub compressed_size: u32,
     pub decompressed_size: u32,
     pub record_block_type: u32,
-    pub record_start: u32,
-    pub record_end: u32,
-    pub offset: u32,
+    pub record_start: u64,
+    pub record_end: u64,
+    pub offset: u64,
+}
+
+/// where a compressed record block is in the file, `offset` is the position of its first byte
+/// in the concatenated decompressed record data, the unit of `KeyIndex.key_id`
+#[derive(Debug, Clone)]
+pub struct RecordBlockInfo {
+    pub file_pos: u64,
+    pub compressed_size: usize,
+    pub decompressed_size: usize,
+    pub offset: usize,
 }
 
 #[derive(Debug)]
 pub struct Header {
     pub file: String,
     pub genversion: f32,
+    pub requiredversion: f32,
+    pub title: String,
+    /// html describing the dictionary, already unescaped
+    pub description: String,
     pub format: String,
     pub keycasesensitive: bool,
     pub stripkey: bool,
@@ -43,29 +77,40 @@ pub struct Header {
     pub encoding: String,
     pub creationdate: String,
     pub compact: bool,
+    pub compat: bool,
     pub left2right: bool,
     pub datasourceformat: String,
     pub stylesheet: String,
+    /// every attribute of the header element, entities unescaped, including the ones above
+    pub attributes: HashMap<String, String>,
+    /// StyleSheet parsed into `number -> (begin, end)`, used to expand `` `n` `` markers in compact records
+    pub styles: HashMap<u32, (String, String)>,
     pub key_block_offset: u64,
     pub record_block_offset: u64,
 }
 
 #[derive(Debug, Default)]
 pub struct HeaderBuilder {
-    pub file: String,
-    pub genversion: f32,
-    pub format: String,
-    pub keycasesensitive: bool,
-    pub stripkey: bool,
-    pub encrypted: String,
-    pub registerby: String,
-    pub encoding: String,
-    pub compact: bool,
-    pub left2right: bool,
-    pub datasourceformat: String,
-    pub stylesheet: String,
-    pub key_block_offset: u64,
-    pub record_block_offset: u64,
+    pub(crate) file: String,
+    pub(crate) genversion: f32,
+    pub(crate) requiredversion: f32,
+    pub(crate) title: String,
+    pub(crate) description: String,
+    pub(crate) format: String,
+    pub(crate) keycasesensitive: bool,
+    pub(crate) stripkey: bool,
+    pub(crate) encrypted: String,
+    pub(crate) registerby: String,
+    pub(crate) encoding: String,
+    pub(crate) creationdate: String,
+    pub(crate) compact: bool,
+    pub(crate) compat: bool,
+    pub(crate) left2right: bool,
+    pub(crate) datasourceformat: String,
+    pub(crate) stylesheet: String,
+    pub(crate) attributes: HashMap<String, String>,
+    pub(crate) key_block_offset: u64,
+    pub(crate) record_block_offset: u64,
 }
 
 impl HeaderBuilder {
@@ -77,6 +122,18 @@ impl HeaderBuilder {
         self.genversion = genversion;
         self
     }
+    pub fn requiredversion(&mut self, requiredversion: f32) -> &mut Self {
+        self.requiredversion = requiredversion;
+        self
+    }
+    pub fn title(&mut self, title: String) -> &mut Self {
+        self.title = title;
+        self
+    }
+    pub fn description(&mut self, description: String) -> &mut Self {
+        self.description = description;
+        self
+    }
     pub fn format(&mut self, format: String) -> &mut Self {
         self.format = format;
         self
@@ -101,10 +158,18 @@ impl HeaderBuilder {
         self.encoding = encoding;
         self
     }
+    pub fn creationdate(&mut self, creationdate: String) -> &mut Self {
+        self.creationdate = creationdate;
+        self
+    }
     pub fn compact(&mut self, compact: bool) -> &mut Self {
         self.compact = compact;
         self
     }
+    pub fn compat(&mut self, compat: bool) -> &mut Self {
+        self.compat = compat;
+        self
+    }
     pub fn left2right(&mut self, left2right: bool) -> &mut Self {
         self.left2right = left2right;
         self
@@ -117,6 +182,10 @@ impl HeaderBuilder {
         self.stylesheet = stylesheet;
         self
     }
+    pub fn attributes(&mut self, attributes: HashMap<String, String>) -> &mut Self {
+        self.attributes = attributes;
+        self
+    }
     pub fn key_block_offset(&mut self, key_block_offset: u64) -> &mut Self {
         self.key_block_offset = key_block_offset;
         self
@@ -129,28 +198,58 @@ impl HeaderBuilder {
         Header {
             file: self.file.to_owned(),
             genversion: self.genversion,
+            requiredversion: self.requiredversion,
+            title: self.title.to_owned(),
+            description: self.description.to_owned(),
             format: self.format.to_owned(),
             keycasesensitive: self.keycasesensitive,
             stripkey: self.stripkey,
             encrypted: self.encrypted.to_owned(),
             registerby: self.registerby.to_owned(),
             encoding: self.encoding.to_owned(),
-            creationdate: "".to_string(),
+            creationdate: self.creationdate.to_owned(),
             compact: self.compact,
+            compat: self.compat,
             left2right: self.left2right,
             datasourceformat: self.datasourceformat.to_owned(),
             stylesheet: self.stylesheet.to_owned(),
+            attributes: self.attributes.clone(),
+            styles: parse_stylesheet(&self.stylesheet),
             key_block_offset: self.key_block_offset,
             record_block_offset: self.record_block_offset,
         }
     }
 }
 
+impl Header {
+    /// mdd files and some mdx files store key text in utf-16 little endian
+    pub fn is_utf16(&self) -> bool {
+        self.encoding.eq_ignore_ascii_case("UTF-16")
+    }
+
+    /// Encrypted is a number flag in v2 files, v1 files may use Yes/No
+    pub fn encrypted_flag(&self) -> u32 {
+        match self.encrypted.as_str() {
+            "Yes" => 1,
+            "No" | "" => 0,
+            e => e.parse::<u32>().unwrap_or(0),
+        }
+    }
+}
+
 pub struct KeyIndex {
     pub key_id: u64,
     pub key_text: String,
 }
 
+/// one entry of the key block info, checked against the block when it is decoded
+#[derive(Debug, Clone, Copy)]
+pub struct KeyBlockInfo {
+    pub num_entries: u64,
+    pub compressed_size: usize,
+    pub decompressed_size: usize,
+}
+
 pub struct Mdx {
     pub filename: String,
     pub header: Header,
@@ -163,143 +262,212 @@ pub struct Mdx {
     pub num_record_blocks: u64,
     pub keys: Vec<KeyIndex>,
     pub records: Vec<RecordIndex>,
+    pub record_blocks: Vec<RecordBlockInfo>,
+    pub key_block_info: Vec<KeyBlockInfo>,
 }
 
-impl Mdx {
-    pub(crate) fn new(file: &str) -> Mdx {
-        let mut hb = HeaderBuilder::default();
-        hb.file(file.to_string());
-        let mut reader = BufReader::new(File::open(&file).unwrap());
-        let mut _bytes4 = [0; 4];
-        reader.read_exact(&mut _bytes4).expect("read_exact error"); // read exactly 4 bytes
-        let header_len = unpack_u32(&_bytes4, Endian::BE);
+/// block counts and sizes of a file, `info` on the command line and `/stats` on the server
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    pub file_size: u64,
+    pub entries: u64,
+    pub key_blocks: u64,
+    pub record_blocks: u64,
+    pub key_blocks_compressed: u64,
+    pub key_blocks_decompressed: u64,
+    pub record_blocks_compressed: u64,
+    pub record_blocks_decompressed: u64,
+    /// decompressed / compressed size of the record blocks, just under 1.0 when they are stored uncompressed
+    pub compression_ratio: f64,
+}
 
-        let mut header_bytes = vec![0; header_len as usize];
-        reader.read_exact(&mut header_bytes).expect("read header bytes error");
+/// header section: u32 big endian length, utf-16 xml text ending with "\x00\x00", adler32 of the text
+fn read_header<R: Read>(reader: &mut Cursor<R>, hb: &mut HeaderBuilder) -> Result<()> {
+    let header_len = reader.u32(Endian::BE, "header length")? as u64;
+    check_size(header_len, MAX_HEADER_SIZE, "header")?;
+    let header_bytes = reader.read(header_len, "header")?;
 
-        // reade 4 bytes: adler32 checksum of header, in little endian
-        let mut adler32_bytes = [0; 4];
-        reader.read_exact(&mut adler32_bytes).expect("read adler32_bytes error");
+    // reade 4 bytes: adler32 checksum of header, in little endian
+    let adler32_bytes = reader.read(4, "header checksum")?;
+    if !adler32_checksum(&header_bytes, &adler32_bytes, Endian::LE) {
+        return Err(MdxError::Checksum("header"));
+    }
+    debug!("header bytes adler32_checksum success");
 
-        if !adler32_checksum(&header_bytes, &adler32_bytes, Endian::LE) {
-            panic!("unrecognized format");
-        } else {
-            println!("header bytes adler32_checksum success")
-        }
+    if header_bytes.len() < 2 || header_bytes.len() % 2 != 0 {
+        return Err(MdxError::Invalid(format!("header text is {} bytes, not utf-16", header_bytes.len())));
+    }
+    let (header, _end) = header_bytes.split_at(header_bytes.len() - 2);
+    let header_txt = utf16_le_string(&header).ok_or_else(|| MdxError::Invalid("header is not utf-16 text".to_string()))?;
+    extract_header(hb, header_txt)
+}
+
+/// the header of an mdx file held in memory, the fuzz target for header parsing
+#[cfg(feature = "fuzzing")]
+pub fn parse_header(bytes: &[u8]) -> Result<Header> {
+    let mut reader = Cursor::new(bytes);
+    let mut hb = HeaderBuilder::default();
+    read_header(&mut reader, &mut hb)?;
+    Ok(hb.build())
+}
 
-        let current_pos = reader.seek(SeekFrom::Current(0)).expect("Could not get current file position!");
-        hb.key_block_offset(current_pos);
+impl Mdx {
+    /// parse an mdx (or mdd) file, key and record blocks are decompressed on a `Workers::from_env` pool
+    pub fn open(file: &str) -> Result<Mdx> {
+        Mdx::open_with(file, &Workers::from_env())
+    }
 
-        // header text in utf-16 encoding ending with '\x00\x00'
-        let (header, _end) = header_bytes.split_at(header_bytes.len() - 2);
-        let header_txt = utf16_le_string(&header).expect("convert slice to utf16 string in little endian");
+    /// same as `open`, key and record blocks are decompressed on `workers`.
+    /// every size and offset read from the file is checked before it is used, a corrupt or hostile
+    /// file gives an error instead of a panic or a huge allocation
+    pub fn open_with(file: &str, workers: &Workers) -> Result<Mdx> {
+        let mut hb = HeaderBuilder::default();
+        hb.file(file.to_string());
+        let f = File::open(&file)?;
+        let len = f.metadata()?.len();
+        let mut reader = Cursor::with_len(BufReader::new(f), len);
 
-        extract_header(&mut hb, header_txt);
+        read_header(&mut reader, &mut hb)?;
+        hb.key_block_offset(reader.pos());
+        // mdd keys are resource paths in utf-16, the header has no Encoding attribute
+        if file.to_lowercase().ends_with(".mdd") {
+            hb.encoding("UTF-16".to_string());
+        }
 
         // key block info
         let _num_width = if hb.genversion >= 2.0 { 8 } else { 4 };
         let meta_bytes_size = if hb.genversion >= 2.0 { 8 * 5 } else { 4 * 4 };
-        let mut key_block_info_meta_bytes = vec![0; meta_bytes_size];
-        reader.read_exact(&mut key_block_info_meta_bytes).expect("read key_block_info_meta_bytes error");
-
-        let mut nb = NumberBytes::new(&key_block_info_meta_bytes);
-        let num_key_blocks = nb.read_number(_num_width).unwrap();
-        let _num_entries = nb.read_number(_num_width).unwrap();
+        let key_block_info_meta_bytes = reader.read(meta_bytes_size, "key block info sizes")?;
+        let mut meta = Cursor::new(&key_block_info_meta_bytes).width(_num_width);
+        let num_key_blocks = meta.number("key block info sizes")?;
+        let num_key_entries = meta.number("key block info sizes")?;
         if hb.genversion >= 2.0 {
-            let _key_block_info_decompress_size = nb.read_number(_num_width);
+            let _key_block_info_decompressed_size = meta.number("key block info sizes")?;
         }
-        let key_block_info_size = nb.read_number(_num_width).unwrap();
-        let key_block_size = nb.read_number(_num_width).unwrap();
+        let key_block_info_size = meta.number("key block info sizes")?;
+        let key_block_size = meta.number("key block info sizes")?;
 
-        // reade 4 bytes: adler32 checksum of key block info, in big endian
-        let mut adler32_bytes = [0; 4];
-        reader.read_exact(&mut adler32_bytes).expect("read_exact error");
-
-        if !adler32_checksum(&key_block_info_meta_bytes, &adler32_bytes, Endian::BE) {
-            panic!("key block info adler32_checksum error, unrecognized format");
-        } else {
-            println!("key block info adler32_checksum success")
+        if hb.genversion >= 2.0 {
+            // reade 4 bytes: adler32 checksum of key block info, in big endian
+            let adler32_bytes = reader.read(4, "key block info sizes")?;
+            if !adler32_checksum(&key_block_info_meta_bytes, &adler32_bytes, Endian::BE) {
+                return Err(MdxError::Checksum("key block info sizes"));
+            }
+            debug!("key block info adler32_checksum success")
         }
 
-        let mut key_block_info_bytes = vec![0; key_block_info_size as usize];
-        reader.read_exact(&mut key_block_info_bytes).expect("read_exact error");
-
-        let mut key_block_bytes = vec![0; key_block_size as usize];
-        reader.read_exact(&mut key_block_bytes).expect("read_exact error");
-
-        let current_pos = reader.seek(SeekFrom::Current(0)).expect("Could not get current file position!");
-        hb.record_block_offset(current_pos);
+        // both sizes are checked against the rest of the file before allocating
+        let key_block_info_bytes = reader.read(key_block_info_size, "key block info")?;
+        let key_block_bytes = reader.read(key_block_size, "key blocks")?;
+        hb.record_block_offset(reader.pos());
 
         let header = hb.build();
-        let  key_block_comp_decomp_size_list = decode_key_block_info(&key_block_info_bytes, &header);//(key_block_compressed_size, key_block_decompressed_size)
-        let  key_list = decode_key_block(&key_block_bytes, &key_block_comp_decomp_size_list);
+        let key_block_info = decode_key_block_info(&key_block_info_bytes, &header)?;
+        if key_block_info.len() as u64 != num_key_blocks {
+            return Err(MdxError::Invalid(format!("key block info lists {} blocks, header says {}", key_block_info.len(), num_key_blocks)));
+        }
+        let listed_entries = key_block_info.iter().fold(0u64, |sum, b| sum.saturating_add(b.num_entries));
+        if listed_entries != num_key_entries {
+            return Err(MdxError::Invalid(format!("key block info lists {} entries, header says {}", listed_entries, num_key_entries)));
+        }
+        // every block is checked against its own entry count, so key_list has num_key_entries keys
+        let key_list = decode_key_block(&key_block_bytes, &key_block_info, header.is_utf16(), _num_width, workers)?;
 
         //parse record block
-        let num_record_blocks = read_number(&mut reader, _num_width);
-        let num_entries = read_number(&mut reader, _num_width);
-        let record_block_info_size = read_number(&mut reader, _num_width);
-        let _record_block_size = read_number(&mut reader, _num_width);
-        let mut record_block_comp_decomp_size_list: Vec<(usize, usize)> = vec![];
-        let mut size_counter = 0;
+        let mut reader = reader.width(_num_width);
+        let num_record_blocks = reader.number("record block info sizes")?;
+        let num_entries = reader.number("record block info sizes")?;
+        let record_block_info_size = reader.number("record block info sizes")?;
+        let _record_block_size = reader.number("record block info sizes")?;
+        if num_record_blocks.checked_mul(_num_width as u64 * 2) != Some(record_block_info_size) {
+            return Err(MdxError::Invalid(format!("record block info is {} bytes, {} blocks need {}",
+                                                 record_block_info_size, num_record_blocks, num_record_blocks.saturating_mul(_num_width as u64 * 2))));
+        }
         // read all record_block_info bytes
-        for _i in 0..num_record_blocks {
-            let compressed_size = read_number(&mut reader, _num_width);
-            let decompressed_size = read_number(&mut reader, _num_width);
-            record_block_comp_decomp_size_list.push((compressed_size, decompressed_size));
-            size_counter += _num_width * 2
+        let record_block_info_bytes = reader.read(record_block_info_size, "record block info")?;
+        let mut record_block_info = Cursor::new(&record_block_info_bytes).width(_num_width);
+        let mut record_block_comp_decomp_size_list: Vec<(usize, usize)> = vec![];
+        while !record_block_info.is_empty() {
+            let compressed_size = record_block_info.number("record block info")?;
+            let decompressed_size = record_block_info.number("record block info")?;
+            record_block_comp_decomp_size_list.push((
+                check_size(compressed_size, len, "compressed record block")?,
+                check_size(decompressed_size, MAX_BLOCK_SIZE as u64, "decompressed record block")?,
+            ));
         }
-        assert_eq!(size_counter, record_block_info_size);
 
         // start read record block, decompress it
         let mut record_list: Vec<RecordIndex> = vec![]; // important!
+        let mut record_blocks: Vec<RecordBlockInfo> = Vec::with_capacity(record_block_comp_decomp_size_list.len());
         let mut _record_block_bytes_size_counter = 0;
         let mut i: usize = 0;
         let mut offset: usize = 0;
 
-        let mut record_block_counter = 0;
-        for (c_size, d_size) in record_block_comp_decomp_size_list {
-            let cur_pos = reader.seek(SeekFrom::Current(0)).expect("Could not get current file position!");
-            let mut record_block_compressed: Vec<u8> = vec![0; c_size];
-            reader.read_exact(&mut record_block_compressed).expect("read_exact error");
+        let mut record_block_counter: u64 = 0;
+        // blocks are read in batches and decompressed on the worker pool, only their type is kept here
+        for batch in record_block_comp_decomp_size_list.chunks(workers.batch_size()) {
+            let mut batch_blocks: Vec<(Vec<u8>, usize)> = Vec::with_capacity(batch.len());
+            let mut batch_pos: Vec<u64> = Vec::with_capacity(batch.len());
+            for (c_size, d_size) in batch {
+                let cur_pos = reader.pos();
+                let record_block_compressed = reader.read(*c_size as u64, "record block")?;
+                batch_pos.push(cur_pos);
+                batch_blocks.push((record_block_compressed, *d_size));
+            }
+            let block_types = workers.map(batch_blocks, |(b, d_size)| decompress_record_block_bytes(&b, d_size).map(|d| d.1));
+
+            for ((&(c_size, d_size), cur_pos), block_typ) in batch.iter().zip(batch_pos).zip(block_types) {
+                let block_typ = block_typ?;
+                record_blocks.push(RecordBlockInfo {
+                    file_pos: cur_pos,
+                    compressed_size: c_size,
+                    decompressed_size: d_size,
+                    offset,
+                });
 
-            let (_record_block_decompressed, block_typ) = decompress_record_block_bytes(&mut record_block_compressed);
+                // split record block into record according to the offset info from key block
+                while i < key_list.len() {
+                    let key_index = &key_list[i];
+                    let start = key_index.key_id as usize;
+                    if start < offset {
+                        return Err(MdxError::Invalid(format!("record of {:?} starts before its record block", key_index.key_text)));
+                    }
+                    if start - offset >= d_size {
+                        break;
+                    }
+                    let mut record_end: usize = 0;
+                    if i < key_list.len() - 1 {
+                        record_end = key_list[i + 1].key_id as usize;
+                    } else {
+                        record_end = d_size + offset;
+                    }
+                    if record_end < start || record_end > offset + d_size {
+                        return Err(MdxError::Invalid(format!("record of {:?} ends outside its record block", key_index.key_text)));
+                    }
+                    let idx = RecordIndex {
+                        key_text: key_index.key_text.to_string(),
+                        file_pos: cur_pos,
+                        compressed_size: c_size as u32,
+                        decompressed_size: d_size as u32,
+                        record_block_type: block_typ as u32,
+                        record_start: key_index.key_id,
+                        record_end: record_end as u64,
+                        offset: offset as u64,
+                    };
+                    i += 1;
 
-            // split record block into record according to the offset info from key block
-            while i < key_list.len() {
-                let key_index = &key_list[i];
-                let start = key_index.key_id as usize;
-                if start - offset >= d_size {
-                    break;
-                }
-                let mut record_end: usize = 0;
-                if i < key_list.len() - 1 {
-                    record_end = key_list[i + 1].key_id as usize;
-                } else {
-                    record_end = d_size + offset;
+                    // let mut record = &record_block_decompressed[(start - offset)..(record_end - offset)];
+                    // let content = String::from_utf8_lossy(record);
+                    record_list.push(idx)
                 }
-                let idx = RecordIndex {
-                    key_text: key_index.key_text.to_string(),
-                    file_pos: cur_pos as u32,
-                    compressed_size: c_size as u32,
-                    decompressed_size: d_size as u32,
-                    record_block_type: block_typ as u32,
-                    record_start: key_index.key_id.clone() as u32,
-                    record_end: record_end as u32,
-                    offset: offset as u32,
-                };
-                i += 1;
-
-                // let mut record = &record_block_decompressed[(start - offset)..(record_end - offset)];
-                // let content = String::from_utf8_lossy(record);
-                record_list.push(idx)
+                offset += d_size;
+                _record_block_bytes_size_counter += c_size;
+                record_block_counter += 1;
             }
-            offset += d_size;
-            _record_block_bytes_size_counter += c_size;
-            record_block_counter += 1;
         }
 
         let version = header.genversion;
-        Mdx {
+        Ok(Mdx {
             filename: file.to_string(),
             header,
             passcode: "".to_string(),
@@ -307,178 +475,474 @@ impl Mdx {
             number_width: _num_width as i32,
             num_entries: num_entries as u64,
             num_key_blocks: num_key_blocks,
-            num_record_blocks: 122,
+            num_record_blocks: record_block_counter,
             keys: key_list,
             records: record_list,
+            record_blocks,
+            key_block_info,
+        })
+    }
+
+    pub fn stats(&self) -> Stats {
+        let record_blocks_compressed: u64 = self.record_blocks.iter().map(|b| b.compressed_size as u64).sum();
+        let record_blocks_decompressed: u64 = self.record_blocks.iter().map(|b| b.decompressed_size as u64).sum();
+        Stats {
+            file_size: std::fs::metadata(&self.filename).map(|m| m.len()).unwrap_or(0),
+            entries: self.num_entries,
+            key_blocks: self.num_key_blocks,
+            record_blocks: self.num_record_blocks,
+            key_blocks_compressed: self.key_block_info.iter().map(|b| b.compressed_size as u64).sum(),
+            key_blocks_decompressed: self.key_block_info.iter().map(|b| b.decompressed_size as u64).sum(),
+            record_blocks_compressed,
+            record_blocks_decompressed,
+            compression_ratio: if record_blocks_compressed == 0 { 1.0 } else {
+                record_blocks_decompressed as f64 / record_blocks_compressed as f64
+            },
+        }
+    }
+
+    /// every `(headword, definition)` in file order. record blocks are read and decompressed one at a
+    /// time when the iterator reaches them, so only one block is held in memory
+    pub fn entries(&self) -> Entries<'_> {
+        self.entries_on(None)
+    }
+
+    /// `entries` decompressing the next `workers.batch_size()` record blocks at a time on the pool,
+    /// for exports and index builds that go through the whole file
+    pub fn par_entries<'a>(&'a self, workers: &'a Workers) -> Entries<'a> {
+        self.entries_on(Some(workers))
+    }
+
+    fn entries_on<'a>(&'a self, workers: Option<&'a Workers>) -> Entries<'a> {
+        let reader = match File::open(&self.filename) {
+            Ok(f) => Some(BufReader::new(f)),
+            Err(e) => {
+                warn!("open {} error: {}", self.filename, e);
+                None
+            }
+        };
+        Entries {
+            mdx: self,
+            reader,
+            workers,
+            decoded: VecDeque::new(),
+            next_block: 0,
+            block: vec![],
+            block_offset: 0,
+            next_key: 0,
         }
     }
 
+    /// records of `word` in file order, an exact match on the headword. homographs are separate
+    /// entries with the same headword
+    pub fn find(&self, word: &str) -> Vec<&RecordIndex> {
+        self.records.iter().filter(|r| r.key_text == word).collect()
+    }
+
+    /// definitions of `word` in file order, empty if it is not a headword. a `@@@LINK=` redirect
+    /// is returned as it is, `Dict::lookup` follows them
+    pub fn lookup(&self, word: &str) -> Result<Vec<String>> {
+        self.find(word).into_iter().map(|idx| self.read_definition(idx)).collect()
+    }
+
+    /// raw bytes of one record, its block is read from the file and decompressed
+    pub fn read_record(&self, idx: &RecordIndex) -> Result<Vec<u8>> {
+        let mut reader = BufReader::new(File::open(&self.filename)?);
+        reader.seek(SeekFrom::Start(idx.file_pos))?;
+
+        let mut record_block_compressed: Vec<u8> = vec![0; idx.compressed_size as usize];
+        reader.read_exact(&mut record_block_compressed)?;
+        Mdx::extract_record(&record_block_compressed,
+                            idx.decompressed_size as usize,
+                            idx.record_start as usize,
+                            idx.record_end as usize,
+                            idx.offset as usize)
+    }
+
+    /// `read_record` as text with the header stylesheet applied
+    pub fn read_definition(&self, idx: &RecordIndex) -> Result<String> {
+        let record = self.read_record(idx)?;
+        Ok(Mdx::styled(&record, &self.header.styles))
+    }
+
+    // util function, extract the raw record bytes (mdd resource or mdx definition) from a record block
+    pub fn extract_record(record_block_compressed: &[u8], decompressed_size: usize,
+                          record_start: usize, record_end: usize, offset: usize) -> Result<Vec<u8>> {
+        let (record_block_decompressed, _type) = decompress_record_block_bytes(record_block_compressed, decompressed_size)?;
+        let s = record_start.checked_sub(offset).ok_or_else(|| MdxError::Invalid("record starts before its block".to_string()))?;
+        let len = record_end.checked_sub(record_start).ok_or_else(|| MdxError::Invalid("record ends before it starts".to_string()))?;
+        let mut block = Cursor::new(&record_block_decompressed);
+        block.skip(s, "record")?;
+        Ok(block.take(len, "record")?.to_vec())
+    }
 
     // util function, extract word definitions from bytes
-    pub fn extract_definition(record_block_compressed: &mut Vec<u8>, record_start: usize, record_end: usize, offset: usize) -> String {
-        let record_block_type = &record_block_compressed[0..4];
-        let adler32_bytes = &record_block_compressed[4..8];
-        let mut record_block_decompressed = Vec::new();
-        assert_eq!(b"\x02\x00\x00\x00", record_block_type);
-
-        let mut z = ZlibDecoder::new(record_block_decompressed);
-        z.write_all(&record_block_compressed[8..]).unwrap();
-        record_block_decompressed = z.finish().unwrap();
-        if !adler32_checksum(&record_block_decompressed, &adler32_bytes, Endian::BE) {
-            panic!("record block adler32 checksum failed");
-        }
-        let s = record_start - offset;
-        let e = record_end - offset;
-        let record = &record_block_decompressed[s..e];
+    // styles is the parsed header StyleSheet, compact dictionaries need it to restore the html
+    pub fn extract_definition(record_block_compressed: &[u8], decompressed_size: usize,
+                              record_start: usize, record_end: usize, offset: usize,
+                              styles: &HashMap<u32, (String, String)>) -> Result<String> {
+        let record = Mdx::extract_record(record_block_compressed, decompressed_size, record_start, record_end, offset)?;
+        Ok(Mdx::styled(&record, styles))
+    }
+
+    fn styled(record: &[u8], styles: &HashMap<u32, (String, String)>) -> String {
         let def = String::from_utf8_lossy(record);
-        def.to_string()
+        if styles.is_empty() {
+            return def.to_string();
+        }
+        substitute_stylesheet(&def, styles)
+    }
+}
+
+/// streaming iterator returned by `Mdx::entries`, stops early if the file can not be read
+pub struct Entries<'a> {
+    mdx: &'a Mdx,
+    reader: Option<BufReader<File>>,
+    workers: Option<&'a Workers>,
+    // blocks decompressed ahead by the workers, with their offsets
+    decoded: VecDeque<(usize, Vec<u8>)>,
+    next_block: usize,
+    // decompressed bytes of the current record block
+    block: Vec<u8>,
+    block_offset: usize,
+    next_key: usize,
+}
+
+impl<'a> Entries<'a> {
+    fn read_block(&mut self) -> Option<()> {
+        if self.decoded.is_empty() {
+            self.decode_batch()?;
+        }
+        let (offset, block) = self.decoded.pop_front()?;
+        self.block = block;
+        self.block_offset = offset;
+        Some(())
+    }
+
+    // read the next batch of compressed blocks, one block without workers, and decompress them
+    fn decode_batch(&mut self) -> Option<()> {
+        let batch = self.workers.map(|w| w.batch_size()).unwrap_or(1);
+        let infos = self.mdx.record_blocks.iter().skip(self.next_block).take(batch);
+        let reader = self.reader.as_mut()?;
+        let mut compressed_blocks = vec![];
+        let mut offsets = vec![];
+        for info in infos {
+            let mut compressed = vec![0; info.compressed_size];
+            let d_size = info.decompressed_size;
+            let read = reader.seek(SeekFrom::Start(info.file_pos)).and_then(|_| reader.read_exact(&mut compressed));
+            if let Err(e) = read {
+                warn!("read record block #{} of {} error: {}", self.next_block, self.mdx.filename, e);
+                self.reader = None;
+                break;
+            }
+            compressed_blocks.push((compressed, d_size));
+            offsets.push(info.offset);
+            self.next_block += 1;
+        }
+        if compressed_blocks.is_empty() {
+            return None;
+        }
+        let decompress: fn((Vec<u8>, usize)) -> Result<Vec<u8>> = |(c, d_size)| decompress_record_block_bytes(&c, d_size).map(|d| d.0);
+        let blocks = match self.workers {
+            Some(w) => w.map(compressed_blocks, decompress),
+            None => compressed_blocks.into_iter().map(decompress).collect(),
+        };
+        for (offset, block) in offsets.into_iter().zip(blocks) {
+            match block {
+                Ok(block) => self.decoded.push_back((offset, block)),
+                Err(e) => {
+                    // blocks after a broken one are not read, the iterator ends there
+                    warn!("record block at offset {} of {}: {}", offset, self.mdx.filename, e);
+                    self.reader = None;
+                    break;
+                }
+            }
+        }
+        if self.decoded.is_empty() { None } else { Some(()) }
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (String, String);
+
+    fn next(&mut self) -> Option<(String, String)> {
+        loop {
+            let key = self.mdx.keys.get(self.next_key)?;
+            let start = key.key_id as usize;
+            let block_end = self.block_offset + self.block.len();
+            if start < self.block_offset {
+                warn!("record of {} is before its record block, skipped", key.key_text);
+                self.next_key += 1;
+                continue;
+            }
+            if start >= block_end {
+                self.read_block()?;
+                continue;
+            }
+            // a record ends where the next one starts, the last record of a block at the block end
+            let end = match self.mdx.keys.get(self.next_key + 1) {
+                Some(k) if (k.key_id as usize) >= start && (k.key_id as usize) < block_end => k.key_id as usize,
+                _ => block_end,
+            };
+            self.next_key += 1;
+            let record = &self.block[start - self.block_offset..end - self.block_offset];
+            let def = String::from_utf8_lossy(record);
+            let def = if self.mdx.header.styles.is_empty() {
+                def.to_string()
+            } else {
+                substitute_stylesheet(&def, &self.mdx.header.styles)
+            };
+            return Some((key.key_text.clone(), def));
+        }
+    }
+}
+
+/// StyleSheet attribute is groups of 3 lines: style number, begin html, end html
+fn parse_stylesheet(stylesheet: &str) -> HashMap<u32, (String, String)> {
+    let mut styles = HashMap::new();
+    let lines: Vec<&str> = stylesheet.lines().collect();
+    for group in lines.chunks(3) {
+        if group.len() < 3 {
+            break;
+        }
+        if let Ok(n) = group[0].trim().parse::<u32>() {
+            styles.insert(n, (group[1].to_string(), group[2].to_string()));
+        }
+    }
+    styles
+}
+
+/// replace every `` `n` `` marker with the begin html of style n, and close it with the end html
+/// before the next marker, same as readmdict `_substitute_stylesheet`
+pub fn substitute_stylesheet(txt: &str, styles: &HashMap<u32, (String, String)>) -> String {
+    let mut styled = String::with_capacity(txt.len());
+    let mut pending: Option<&(String, String)> = None;
+    let mut last = 0;
+    for cap in STYLE_MARKER.captures_iter(txt) {
+        let m = cap.get(0).unwrap();
+        push_styled(&mut styled, &txt[last..m.start()], pending);
+        pending = cap[1].parse::<u32>().ok().and_then(|n| styles.get(&n));
+        last = m.end();
     }
+    push_styled(&mut styled, &txt[last..], pending);
+    styled
 }
 
-fn decompress_record_block_bytes(record_block_compressed: &mut Vec<u8>) -> (Vec<u8>, i32) {
-    let record_block_type = &record_block_compressed[0..4];
-    let adler32_bytes = &record_block_compressed[4..8];
+fn push_styled(styled: &mut String, part: &str, style: Option<&(String, String)>) {
+    match style {
+        Some((begin, end)) => {
+            styled.push_str(begin);
+            if part.ends_with('\n') {
+                styled.push_str(part.trim_end());
+                styled.push_str(end);
+                styled.push_str("\r\n");
+            } else {
+                styled.push_str(part);
+                styled.push_str(end);
+            }
+        }
+        None => styled.push_str(part),
+    }
+}
+
+/// zlib inflate that stops at `limit` output bytes, a small block can not expand to gigabytes
+pub(crate) fn inflate(data: &[u8], limit: usize, what: &'static str) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    ZlibDecoder::new(data).take(limit as u64 + 1).read_to_end(&mut decompressed)
+        .map_err(|e| MdxError::Zlib { what, message: e.to_string() })?;
+    if decompressed.len() > limit {
+        return Err(MdxError::TooLarge { what, size: decompressed.len() as u64, limit: limit as u64 });
+    }
+    Ok(decompressed)
+}
+
+/// decompressed bytes and type of a record block, `decompressed_size` from the record block info
+/// caps the output
+pub fn decompress_record_block_bytes(record_block_compressed: &[u8], decompressed_size: usize) -> Result<(Vec<u8>, i32)> {
+    let mut block = Cursor::new(record_block_compressed);
+    let record_block_type = block.take(4, "record block type")?;
+    let adler32_bytes = block.take(4, "record block checksum")?;
+    let data = block.rest();
     let mut record_block_decompressed = Vec::new();
     let mut _type = 2;
     match record_block_type {
         b"\x02\x00\x00\x00" => {
             _type = 2;
-            let mut z = ZlibDecoder::new(record_block_decompressed);
-            z.write_all(&record_block_compressed[8..]).unwrap();
-            record_block_decompressed = z.finish().unwrap();
-            if !adler32_checksum(&record_block_decompressed, &adler32_bytes, Endian::BE) {
-                panic!("record block adler32 checksum failed");
+            record_block_decompressed = inflate(data, decompressed_size.min(MAX_BLOCK_SIZE), "record block")?;
+            if !adler32_checksum(&record_block_decompressed, adler32_bytes, Endian::BE) {
+                return Err(MdxError::Checksum("record block"));
             }
         }
-        b"\x01\x00\x00\x00" => { println!("\x01\x00\x00\x00") }
-        b"\x00\x00\x00\x00" => { println!("\x00\x00\x00\x00") }
-        _ => {}
+        b"\x01\x00\x00\x00" => {
+            _type = 1;
+            warn!("lzo compressed record block is not supported")
+        }
+        b"\x00\x00\x00\x00" => {
+            // stored without compression, common in mdd files for already compressed media
+            _type = 0;
+            record_block_decompressed = data.to_vec();
+        }
+        t => return Err(MdxError::Invalid(format!("unknown record block type {:?}", t))),
     }
-    (record_block_decompressed, _type)
+    Ok((record_block_decompressed, _type))
 }
 
-fn extract_header(hb: &mut HeaderBuilder, header_txt: String) {
-    let mut _header_map = HashMap::new();
-    let re = Regex::new(r#"(\w+)=["](.*?)["]"#).unwrap();
-    let cap_matches = re.captures_iter(header_txt.as_str());
-    for cap in cap_matches {
-        _header_map.insert(cap.get(1).unwrap().as_str(), cap.get(2).unwrap().as_str());
-    }
+pub(crate) fn extract_header(hb: &mut HeaderBuilder, header_txt: String) -> Result<()> {
+    // Description and StyleSheet values are escaped html, parse_attributes unescapes them
+    let _header_map = parse_attributes(&header_txt);
 
-    if let Some(v) = _header_map.get(&"GeneratedByEngineVersion") {
-        hb.genversion(v.parse::<f32>().unwrap());
+    if let Some(v) = _header_map.get("GeneratedByEngineVersion") {
+        let version = v.trim().parse::<f32>()
+            .map_err(|_| MdxError::Invalid(format!("GeneratedByEngineVersion {:?} is not a number", v)))?;
+        hb.genversion(version);
+    }
+    if let Some(v) = _header_map.get("RequiredEngineVersion") {
+        hb.requiredversion(v.parse::<f32>().unwrap_or(0.0));
+    }
+    if let Some(t) = _header_map.get("Title") {
+        hb.title(t.to_string());
+    }
+    if let Some(d) = _header_map.get("Description") {
+        hb.description(d.to_string());
+    }
+    if let Some(c) = _header_map.get("CreationDate") {
+        hb.creationdate(c.to_string());
+    }
+    if let Some(c) = _header_map.get("Compat") {
+        hb.compat(c == "Yes");
     }
 
-    if let Some(f) = _header_map.get(&"Format") {
+    if let Some(f) = _header_map.get("Format") {
         hb.format(f.to_string());
     }
-    if let Some(k) = _header_map.get(&"KeyCaseSensitive") {
-        if k == &"Yes" {
+    if let Some(k) = _header_map.get("KeyCaseSensitive") {
+        if k == "Yes" {
             hb.keycasesensitive(true);
         } else {
             hb.keycasesensitive(false);
         }
     }
-    if let Some(s) = _header_map.get(&"StripKey") {
-        if s == &"Yes" {
+    if let Some(s) = _header_map.get("StripKey") {
+        if s == "Yes" {
             hb.stripkey(true);
         } else {
             hb.stripkey(false);
         }
     }
-    if let Some(en) = _header_map.get(&"Encrypted") {
+    if let Some(en) = _header_map.get("Encrypted") {
         hb.encrypted(en.to_string());
     }
-    if let Some(r) = _header_map.get(&"RegisterBy") {
+    if let Some(r) = _header_map.get("RegisterBy") {
         hb.registerby(r.to_string());
     }
-    if let Some(encoding) = _header_map.get(&"Encoding") {
+    if let Some(encoding) = _header_map.get("Encoding") {
         hb.encoding(encoding.to_string());
     }
-    if let Some(d) = _header_map.get(&"DataSourceFormat") {
+    if let Some(d) = _header_map.get("DataSourceFormat") {
         hb.datasourceformat(d.to_string());
     }
-    if let Some(s) = _header_map.get(&"StyleSheet") {
+    if let Some(s) = _header_map.get("StyleSheet") {
         hb.stylesheet(s.to_string());
     }
-    if let Some(c) = _header_map.get(&"Compact") { //or Compat
-        if c == &"Yes" {
+    if let Some(c) = _header_map.get("Compact") { //or Compat
+        if c == "Yes" {
             hb.compact(true);
         } else {
             hb.compact(false);
         }
     }
-    if let Some(l) = _header_map.get(&"Left2Right") {
-        if l == &"Yes" {
+    if let Some(l) = _header_map.get("Left2Right") {
+        if l == "Yes" {
             hb.left2right(true);
         } else {
             hb.left2right(false);
         }
     }
+    hb.attributes(_header_map);
+    Ok(())
 }
 
 
-pub fn decode_key_block_info(key_block_info_compressed: &Vec<u8>, header: &Header) -> Vec<(usize, usize)> {
-    let first4 = &key_block_info_compressed[0..4];
-    let mut adler32_bytes = &key_block_info_compressed[4..8];
-    let data = &key_block_info_compressed[8..];
-    let mut decrypt_bytes = vec![0; data.len()];
-    let mut key_block_info_bytes = Vec::new();
+pub fn decode_key_block_info(key_block_info_compressed: &[u8], header: &Header) -> Result<Vec<KeyBlockInfo>> {
+    let mut block = Cursor::new(key_block_info_compressed);
+    let first4 = block.take(4, "key block info type")?;
+    let adler32_bytes = block.take(4, "key block info checksum")?;
+    let key_block_info_bytes;
     if header.genversion >= 2.0 {
-        assert!(b"\x02\x00\x00\x00" == first4);
-        let encrypted = header.encrypted.parse::<u32>().unwrap();
-        if encrypted & 0x02 == 0x02 {
-            let key = get_key_block_info_decrypt_key(&mut adler32_bytes);
-            let mut previous: u8 = 0x36;
-            for i in 0..data.len() {
-                let mut t = (data[i] >> 4 | data[i] << 4) & 0xff;
-                t = t ^ previous ^ (i & 0xff) as u8 ^ key[i % key.len()];
-                previous = data[i].clone();
-                decrypt_bytes[i] = t;
-            }
+        if b"\x02\x00\x00\x00" != first4 {
+            return Err(MdxError::Invalid(format!("key block info is not zlib compressed, type {:?}", first4)));
+        }
+        let decrypt_bytes = decrypt_key_block_info(key_block_info_compressed, header)?;
 
-            let mut z = ZlibDecoder::new(key_block_info_bytes);
-            z.write_all(decrypt_bytes.as_ref()).unwrap();
-            key_block_info_bytes = z.finish().unwrap();
+        //data now is decrypted, then decompress
+        key_block_info_bytes = inflate(&decrypt_bytes, MAX_BLOCK_SIZE, "key block info")?;
 
-            //data now is decrypted, then decompress
-            if !adler32_checksum(&key_block_info_bytes, &adler32_bytes, Endian::BE) {
-                panic!("key_block_info adler32 checksum failed!")
-            }
+        if !adler32_checksum(&key_block_info_bytes, adler32_bytes, Endian::BE) {
+            return Err(MdxError::Checksum("key block info"));
+        }
+    } else {
+        key_block_info_bytes = key_block_info_compressed.to_vec();
+    }
+    parse_key_block_info(&key_block_info_bytes, header)
+}
+
+/// the zlib stream after the 8 bytes type+checksum prefix, decrypted when Encrypted has the 0x02 bit
+pub(crate) fn decrypt_key_block_info(key_block_info_compressed: &[u8], header: &Header) -> Result<Vec<u8>> {
+    let mut block = Cursor::new(key_block_info_compressed);
+    block.skip(4, "key block info type")?;
+    let mut adler32_bytes = block.take(4, "key block info checksum")?;
+    let data = block.rest();
+    let mut decrypt_bytes = vec![0; data.len()];
+    if header.encrypted_flag() & 0x02 == 0x02 {
+        let key = get_key_block_info_decrypt_key(&mut adler32_bytes);
+        let mut previous: u8 = 0x36;
+        for i in 0..data.len() {
+            let mut t = (data[i] >> 4 | data[i] << 4) & 0xff;
+            t = t ^ previous ^ (i & 0xff) as u8 ^ key[i % key.len()];
+            previous = data[i].clone();
+            decrypt_bytes[i] = t;
         }
     } else {
-        key_block_info_bytes = key_block_info_compressed.clone();
+        decrypt_bytes.copy_from_slice(data);
     }
+    Ok(decrypt_bytes)
+}
 
-    //start decode
-    // let mut key_block_info_list = vec![];
-    let mut _num_enteries = 0 as u64;
+/// entry count and sizes of every key block
+pub fn parse_key_block_info(key_block_info_bytes: &[u8], header: &Header) -> Result<Vec<KeyBlockInfo>> {
     let mut byte_width = 1;
     let mut text_term = 0;
     if header.genversion >= 2.0 {
         byte_width = 2;
         text_term = 1;
     }
-    let num_width = 8;
-    let mut i = 0;
-    let mut key_block_info_list: Vec<(usize, usize)> = vec![];
-    while i < key_block_info_bytes.len() {
-        _num_enteries += unpack_u64(&key_block_info_bytes[i..(i + num_width)], Endian::BE);
-        i += num_width;
-        let text_head_size = unpack_u16(&key_block_info_bytes[i..(i + byte_width)], Endian::BE);
-        i += byte_width;
-        i += (text_head_size + text_term) as usize;
-        let text_tail_size = unpack_u16(&key_block_info_bytes[i..(i + byte_width)], Endian::BE);
-        i += byte_width; //todo:
-        i += (text_tail_size + text_term) as usize;
+    // head/tail text size is counted in characters, utf-16 characters take 2 bytes
+    let char_width = if header.is_utf16() { 2 } else { 1 };
+    let num_width = if header.genversion >= 2.0 { 8 } else { 4 };
+    let mut cursor = Cursor::new(key_block_info_bytes).width(num_width);
+    // first and last key of the block, only their sizes are needed to skip them
+    let skip_text = |cursor: &mut Cursor<&[u8]>| -> Result<()> {
+        let text_size = if byte_width == 2 { cursor.u16(Endian::BE, "key block info")? } else { cursor.u8("key block info")? as u16 };
+        cursor.skip((text_size as usize + text_term) * char_width, "key block info")
+    };
+    let mut key_block_info_list: Vec<KeyBlockInfo> = vec![];
+    let mut decompressed_total: u64 = 0;
+    while !cursor.is_empty() {
+        let num_entries = cursor.number("key block info")?;
+        skip_text(&mut cursor)?;
+        skip_text(&mut cursor)?;
 
-        let key_block_compressed_size = unpack_u64(&key_block_info_bytes[i..(i + num_width)], Endian::BE);
-        i += num_width;
-        let key_block_decompressed_size = unpack_u64(&key_block_info_bytes[i..(i + num_width)], Endian::BE);
-        i += num_width;
-        key_block_info_list.push((key_block_compressed_size as usize, key_block_decompressed_size as usize))
+        let key_block_compressed_size = cursor.number("key block info")?;
+        let key_block_decompressed_size = cursor.number("key block info")?;
+        decompressed_total = decompressed_total.saturating_add(key_block_decompressed_size);
+        check_size(decompressed_total, MAX_KEY_BLOCKS_SIZE, "decompressed key blocks")?;
+        key_block_info_list.push(KeyBlockInfo {
+            num_entries,
+            compressed_size: key_block_compressed_size as usize,
+            decompressed_size: check_size(key_block_decompressed_size, MAX_BLOCK_SIZE as u64, "decompressed key block")?,
+        })
     }
-    return key_block_info_list;
+    Ok(key_block_info_list)
 }
 
 
@@ -494,65 +958,203 @@ fn get_key_block_info_decrypt_key(adler32_bytes: &mut &[u8]) -> Vec<u8> {
     ga.as_slice().iter().cloned().collect()
 }
 
-fn decode_key_block(all_key_block_bytes: &Vec<u8>, key_block_info_list: &Vec<(usize, usize)>) -> Vec<KeyIndex> {
+fn decode_key_block(all_key_block_bytes: &[u8], key_block_info_list: &[KeyBlockInfo], utf16: bool, num_width: usize,
+                    workers: &Workers) -> Result<Vec<KeyIndex>> {
+    let mut blocks: Vec<(Vec<u8>, KeyBlockInfo, bool, usize)> = Vec::with_capacity(key_block_info_list.len());
+    let mut cursor = Cursor::new(all_key_block_bytes);
+    for info in key_block_info_list {
+        let block = cursor.take(info.compressed_size, "key blocks")?;
+        blocks.push((block.to_vec(), *info, utf16, num_width));
+    }
+    if !cursor.is_empty() {
+        return Err(MdxError::Invalid(format!("{} bytes after the last key block", cursor.remaining())));
+    }
+    // every block is decompressed and split on the pool, the key lists are joined in block order.
+    // nothing is reserved from the key block info, its counts and sizes are only checked once a
+    // block was decoded
+    let blocks = workers.map(blocks, |(block, info, utf16, width)| decode_key_block_bytes(&block, &info, utf16, width))
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+    let mut key_list = Vec::with_capacity(blocks.iter().map(|keys| keys.len()).sum());
+    for keys in blocks {
+        key_list.extend(keys);
+    }
+    Ok(key_list)
+}
+
+/// keys of one key block: type, adler32 and the zlib or raw key data. the data must decompress
+/// to exactly `info.decompressed_size` bytes holding `info.num_entries` keys, key ids are
+/// `num_width` bytes
+pub fn decode_key_block_bytes(one_key_block_bytes: &[u8], info: &KeyBlockInfo, utf16: bool, num_width: usize) -> Result<Vec<KeyIndex>> {
     let mut key_list: Vec<KeyIndex> = vec![];
-    let mut i = 0;
-    let mut start = 0;
-    let mut end = i;
-    for (key_block_compressed_size, _key_block_decompressed_size) in key_block_info_list { //  key_block_decompressed_size is used when key_block_type=b"\x02\x00\x00\x00"
-        start = i;
-        end += *key_block_compressed_size;
-
-        let one_key_block_bytes = &all_key_block_bytes[start as usize..end as usize];
-
-
-        let key_block_type = &one_key_block_bytes[0..4];
-        let _adler32_bytes = &one_key_block_bytes[4..8];
-        let mut key_block = Vec::new();
-
-        match key_block_type {
-            b"\x02\x00\x00\x00" => {
-                let mut z = ZlibDecoder::new(key_block);
-                z.write_all(&one_key_block_bytes[8..]).unwrap();
-                key_block = z.finish().unwrap();
-                split_key_block(&key_block, &mut key_list);
+    let mut block = Cursor::new(one_key_block_bytes);
+    let key_block_type = block.take(4, "key block type")?;
+    let adler32_bytes = block.take(4, "key block checksum")?;
+    let data = block.rest();
+    match key_block_type {
+        b"\x02\x00\x00\x00" => {
+            let key_block = inflate(data, info.decompressed_size.min(MAX_BLOCK_SIZE), "key block")?;
+            if !adler32_checksum(&key_block, adler32_bytes, Endian::BE) {
+                return Err(MdxError::Checksum("key block"));
             }
-            _ => {}
+            check_key_block_size(key_block.len(), info)?;
+            split_key_block(&key_block, &mut key_list, utf16, num_width)?;
         }
-
-        i += *key_block_compressed_size as usize;
+        b"\x00\x00\x00\x00" => {
+            check_key_block_size(data.len(), info)?;
+            split_key_block(data, &mut key_list, utf16, num_width)?;
+        }
+        t => return Err(MdxError::Invalid(format!("unknown key block type {:?}", t))),
+    }
+    if key_list.len() as u64 != info.num_entries {
+        return Err(MdxError::Invalid(format!("key block has {} keys, key block info says {}", key_list.len(), info.num_entries)));
     }
-    return key_list;
+    Ok(key_list)
 }
 
+fn check_key_block_size(size: usize, info: &KeyBlockInfo) -> Result<()> {
+    if size != info.decompressed_size {
+        return Err(MdxError::Invalid(format!("key block is {} bytes, key block info says {}", size, info.decompressed_size)));
+    }
+    Ok(())
+}
 
 /// 将一个key block 中的多个 key_id,key_text解析出来得到一个Vec<KeyIndex>
-fn split_key_block(key_block: &Vec<u8>, key_index_list: &mut Vec<KeyIndex>) {
-    let num_width: usize = 8;
-    let mut key_start = 0; //一个keyIndex的起点
-    let mut key_end = 0;//一个keyIndex的终点
-
-    let delimiter = b"\x00";
-    let delimiter_width = 1;
-    let mut text_start = 0;
-
-    while key_start < key_block.len() {
-        let slice = &key_block[key_start..(key_start + num_width)];
-        let key_id = unpack_u64(slice, Endian::BE);
-
-        text_start = key_start + num_width;
-        while text_start < key_block.len() {
-            if &key_block[text_start..(text_start + delimiter_width)] == delimiter {
-                key_end = text_start;
-                break;
-            }
-            text_start += delimiter_width;
-        }
-        let key_text = std::str::from_utf8(&key_block[(key_start + num_width)..(key_end)]).unwrap().to_string();
-        key_start = key_end + delimiter_width;
+/// utf16 为 true 时 key_text 是 utf-16 编码, 以 b"\x00\x00" 结尾. key_id 是 num_width 字节: v2 为 8, v1 为 4
+pub(crate) fn split_key_block(key_block: &[u8], key_index_list: &mut Vec<KeyIndex>, utf16: bool, num_width: usize) -> Result<()> {
+    let mut cursor = Cursor::new(key_block).width(num_width);
+    let delimiter: &[u8] = if utf16 { b"\x00\x00" } else { b"\x00" };
+
+    while !cursor.is_empty() {
+        let key_start = cursor.pos(); //一个keyIndex的起点
+        let key_id = cursor.number("key id")?;
+        let text_bytes = cursor.take_until(delimiter, "key")?;
+        let key_text = if utf16 {
+            utf16_le_string(text_bytes).ok_or_else(|| MdxError::Invalid(format!("key at offset {} is not utf-16", key_start)))?
+        } else {
+            std::str::from_utf8(text_bytes)
+                .map_err(|_| MdxError::Invalid(format!("key at offset {} is not utf-8", key_start)))?
+                .to_string()
+        };
         key_index_list.push(KeyIndex {
             key_id,
             key_text,
         });
     }
-}
\ No newline at end of file
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use adler32::RollingAdler32;
+    use flate2::Compression;
+    use flate2::write::ZlibEncoder;
+
+    use crate::error::MdxError;
+    use crate::workers::Workers;
+
+    use super::{decode_key_block, decode_key_block_bytes, KeyBlockInfo, parse_stylesheet, split_key_block, substitute_stylesheet};
+
+    // two utf-8 keys with 8 byte ids
+    const KEYS: &[u8] = b"\0\0\0\0\0\0\0\x01apple\0\0\0\0\0\0\0\0\x02bank\0";
+
+    fn raw_block(data: &[u8]) -> Vec<u8> {
+        [&b"\0\0\0\0\0\0\0\0"[..], data].concat()
+    }
+
+    fn zlib_block(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        let checksum = RollingAdler32::from_buffer(data).hash().to_be_bytes();
+        [&b"\x02\0\0\0"[..], &checksum, &encoder.finish().unwrap()].concat()
+    }
+
+    fn info(num_entries: u64, compressed_size: usize, decompressed_size: usize) -> KeyBlockInfo {
+        KeyBlockInfo { num_entries, compressed_size, decompressed_size }
+    }
+
+    // readmdict `_substitute_stylesheet`: the text before the first marker is kept as is, each
+    // part after a marker is wrapped in its style, a part ending in a newline is right-trimmed and
+    // followed by `\r\n`
+    #[test]
+    fn substitute_like_readmdict() {
+        let styles = parse_stylesheet("1\r\n<b>\r\n</b>\r\n2\r\n<i>\r\n</i>\r\n");
+        assert_eq!(substitute_stylesheet("no markers", &styles), "no markers");
+        assert_eq!(substitute_stylesheet("a`1`bold`2`it", &styles), "a<b>bold</b><i>it</i>");
+        assert_eq!(substitute_stylesheet("`1`line  \n`2`end\r\n", &styles), "<b>line</b>\r\n<i>end</i>\r\n");
+        assert_eq!(substitute_stylesheet("`1``2`x", &styles), "<b></b><i>x</i>");
+    }
+
+    #[test]
+    fn unknown_style_is_dropped() {
+        let styles = parse_stylesheet("1\n<b>\n</b>");
+        assert_eq!(substitute_stylesheet("`9`plain`1`bold", &styles), "plain<b>bold</b>");
+    }
+
+    #[test]
+    fn stylesheet_groups() {
+        let styles = parse_stylesheet("1\n<b>\n</b>\nx\n<i>\n</i>\n3\n<u>");
+        assert_eq!(styles.len(), 1);
+        assert_eq!(styles[&1], ("<b>".to_string(), "</b>".to_string()));
+    }
+
+    #[test]
+    fn key_ids_at_number_width() {
+        let mut keys = vec![];
+        split_key_block(b"\0\0\0\x05apple\0\0\0\x01\x00bank\0", &mut keys, false, 4).unwrap();
+        assert_eq!(keys.iter().map(|k| (k.key_id, k.key_text.as_str())).collect::<Vec<_>>(), vec![(5, "apple"), (256, "bank")]);
+        keys.clear();
+        split_key_block(b"\0\0\0\0\0\0\0\x07a\0b\0\0\0", &mut keys, true, 8).unwrap();
+        assert_eq!((keys[0].key_id, keys[0].key_text.as_str()), (7, "ab"));
+    }
+
+    #[test]
+    fn key_blocks_decode() {
+        let raw = raw_block(KEYS);
+        let keys = decode_key_block_bytes(&raw, &info(2, raw.len(), KEYS.len()), false, 8).unwrap();
+        assert_eq!(keys.iter().map(|k| k.key_text.as_str()).collect::<Vec<_>>(), vec!["apple", "bank"]);
+        let zlib = zlib_block(KEYS);
+        let all = [&raw[..], &zlib[..]].concat();
+        let infos = [info(2, raw.len(), KEYS.len()), info(2, zlib.len(), KEYS.len())];
+        assert_eq!(decode_key_block(&all, &infos, false, 8, &Workers::new(2)).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn entry_count_mismatch() {
+        let raw = raw_block(KEYS);
+        let err = decode_key_block_bytes(&raw, &info(3, raw.len(), KEYS.len()), false, 8).err().unwrap();
+        assert!(matches!(&err, MdxError::Invalid(m) if m == "key block has 2 keys, key block info says 3"), "{}", err);
+    }
+
+    #[test]
+    fn decompressed_size_mismatch() {
+        let raw = raw_block(KEYS);
+        assert!(matches!(decode_key_block_bytes(&raw, &info(2, raw.len(), KEYS.len() + 1), false, 8), Err(MdxError::Invalid(_))));
+        let zlib = zlib_block(KEYS);
+        assert!(matches!(decode_key_block_bytes(&zlib, &info(2, zlib.len(), KEYS.len() - 1), false, 8), Err(MdxError::TooLarge { .. })));
+    }
+
+    // a few bytes claiming a gigabyte of keys are rejected without reserving room for them
+    #[test]
+    fn huge_claimed_sizes() {
+        let zlib = zlib_block(KEYS);
+        let claimed = info((1 << 30) / 9, zlib.len(), 1 << 30);
+        assert!(matches!(decode_key_block_bytes(&zlib, &claimed, false, 8), Err(MdxError::Invalid(_))));
+        assert!(decode_key_block(&zlib, &[claimed], false, 8, &Workers::new(1)).is_err());
+    }
+
+    #[test]
+    fn truncated_blocks() {
+        let zlib = zlib_block(KEYS);
+        let infos = [info(2, zlib.len() + 1, KEYS.len())];
+        assert!(matches!(decode_key_block(&zlib, &infos, false, 8, &Workers::new(1)), Err(MdxError::Truncated { what: "key blocks", .. })));
+        let infos = [info(2, zlib.len() - 1, KEYS.len())];
+        assert!(decode_key_block(&zlib, &infos, false, 8, &Workers::new(1)).is_err());
+        assert!(matches!(decode_key_block_bytes(b"\x02\0\0\0\0", &infos[0], false, 8), Err(MdxError::Truncated { .. })));
+        // a key cut off before its terminator
+        let raw = raw_block(&KEYS[..KEYS.len() - 1]);
+        assert!(decode_key_block_bytes(&raw, &info(2, raw.len(), KEYS.len() - 1), false, 8).is_err());
+    }
+}
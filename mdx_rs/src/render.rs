@@ -0,0 +1,415 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    // name=value attributes of a tag, values quoted or not
+    static ref ATTR: Regex = Regex::new(r#"([\w-]+)\s*=\s*("[^"]*"|'[^']*'|[^\s>]+)"#).unwrap();
+}
+
+/// output format of a definition, `?format=` on the api and `--format` on the command line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Html,
+    Text,
+    Markdown,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Format> {
+        match s.to_lowercase().as_str() {
+            "html" => Some(Format::Html),
+            "text" | "txt" => Some(Format::Text),
+            "md" | "markdown" => Some(Format::Markdown),
+            _ => None,
+        }
+    }
+
+    /// the name `parse` reads for the format, `txt` and `markdown` come back as `text` and `md`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Text => "text",
+            Format::Markdown => "md",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Html => "text/html; charset=UTF-8",
+            Format::Text => "text/plain; charset=UTF-8",
+            Format::Markdown => "text/markdown; charset=UTF-8",
+        }
+    }
+}
+
+pub fn render(html: &str, format: Format) -> String {
+    match format {
+        Format::Html => html.trim_end_matches(|c| c == '\0' || c == '\r' || c == '\n').to_string(),
+        Format::Text => html_to_text(html),
+        Format::Markdown => html_to_markdown(html),
+    }
+}
+
+/// several rendered entries of one headword (homographs) as one page: html `<section>`s,
+/// markdown separated by rules, text by blank lines
+pub fn join_sections(entries: &[String], format: Format) -> String {
+    match format {
+        Format::Html => entries.iter()
+            .map(|e| format!("<section class=\"entry\">{}</section>", e))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Format::Text => entries.join("\n\n"),
+        Format::Markdown => entries.join("\n\n---\n\n"),
+    }
+}
+
+/// readable plain text: block tags become line breaks, list items get bullets or numbers
+pub fn html_to_text(html: &str) -> String {
+    Renderer::new(false).run(html)
+}
+
+/// markdown keeping bold/italic, headings, lists, links and images
+pub fn html_to_markdown(html: &str) -> String {
+    Renderer::new(true).run(html)
+}
+
+struct Renderer {
+    md: bool,
+    out: String,
+    // open lists, None for <ul>, Some(n) for <ol> with the next item number
+    lists: Vec<Option<usize>>,
+    // inside <script>/<style>/<head>, their text is not rendered
+    skip: usize,
+    // href of the open <a>, and where its text starts in out
+    link: Option<(String, usize)>,
+}
+
+impl Renderer {
+    fn new(md: bool) -> Renderer {
+        Renderer {
+            md,
+            out: String::new(),
+            lists: vec![],
+            skip: 0,
+            link: None,
+        }
+    }
+
+    fn run(mut self, html: &str) -> String {
+        let mut rest = html;
+        while let Some(lt) = rest.find('<') {
+            self.text(&rest[..lt]);
+            rest = &rest[lt..];
+            if rest.starts_with("<!--") {
+                rest = rest.find("-->").map(|e| &rest[e + 3..]).unwrap_or("");
+                continue;
+            }
+            match tag_end(rest) {
+                Some(gt) => {
+                    self.tag(&rest[1..gt]);
+                    rest = &rest[gt + 1..];
+                }
+                // `a < b`: not a tag, the `<` is text
+                None => {
+                    self.text("<");
+                    rest = &rest[1..];
+                }
+            }
+        }
+        self.text(rest);
+        self.finish()
+    }
+
+    fn text(&mut self, raw: &str) {
+        if self.skip > 0 || raw.is_empty() {
+            return;
+        }
+        let decoded = decode_entities(&raw.replace('\0', ""));
+        for c in decoded.chars() {
+            // html whitespace collapses to one space, &nbsp; (\u{a0}) is kept as a real space
+            if c.is_whitespace() && c != '\u{a0}' {
+                if !self.at_line_start() && !self.out.ends_with(' ') {
+                    self.out.push(' ');
+                }
+                continue;
+            }
+            self.out.push(if c == '\u{a0}' { ' ' } else { c });
+        }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn newline(&mut self) {
+        while self.out.ends_with(' ') {
+            self.out.pop();
+        }
+        if !self.at_line_start() {
+            self.out.push('\n');
+        }
+    }
+
+    fn blank_line(&mut self) {
+        self.newline();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn attr(&self, tag: &str, name: &str) -> Option<String> {
+        ATTR.captures_iter(tag)
+            .find(|c| c[1].eq_ignore_ascii_case(name))
+            .map(|c| decode_entities(c[2].trim_matches(|q| q == '"' || q == '\'')))
+    }
+
+    fn tag(&mut self, tag: &str) {
+        let closing = tag.starts_with('/');
+        let name: String = tag.trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+
+        if ["script", "style", "head"].contains(&name.as_str()) {
+            if closing {
+                self.skip = self.skip.saturating_sub(1);
+            } else if !tag.ends_with('/') {
+                self.skip += 1;
+            }
+            return;
+        }
+        if self.skip > 0 {
+            return;
+        }
+
+        match (name.as_str(), closing) {
+            ("br", _) => self.newline(),
+            ("p", _) | ("div", _) | ("tr", _) | ("blockquote", _) | ("dd", _) | ("dt", _) => self.newline(),
+            ("td", false) | ("th", false) => {
+                if !self.at_line_start() && !self.out.ends_with(' ') {
+                    self.out.push(' ');
+                }
+            }
+            ("hr", _) => {
+                self.blank_line();
+                if self.md {
+                    self.out.push_str("---\n");
+                }
+            }
+            (h, false) if h.len() == 2 && h.starts_with('h') && h[1..].parse::<usize>().is_ok() => {
+                self.blank_line();
+                if self.md {
+                    let level = h[1..].parse::<usize>().unwrap().max(1).min(6);
+                    self.out.push_str(&"#".repeat(level));
+                    self.out.push(' ');
+                }
+            }
+            (h, true) if h.len() == 2 && h.starts_with('h') && h[1..].parse::<usize>().is_ok() => self.blank_line(),
+            ("ul", false) => {
+                self.newline();
+                self.lists.push(None);
+            }
+            ("ol", false) => {
+                self.newline();
+                let start = self.attr(tag, "start").and_then(|s| s.parse::<usize>().ok()).unwrap_or(1);
+                self.lists.push(Some(start));
+            }
+            ("ul", true) | ("ol", true) => {
+                self.lists.pop();
+                self.newline();
+            }
+            ("li", false) => {
+                self.newline();
+                let depth = self.lists.len().max(1) - 1;
+                self.out.push_str(&"  ".repeat(depth));
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => if self.md { "- ".to_string() } else { "• ".to_string() },
+                };
+                self.out.push_str(&bullet);
+            }
+            ("li", true) => self.newline(),
+            ("b", _) | ("strong", _) if self.md => self.out.push_str("**"),
+            ("i", _) | ("em", _) if self.md => self.out.push('_'),
+            ("code", _) if self.md => self.out.push('`'),
+            ("a", false) => {
+                let href = self.attr(tag, "href").unwrap_or_default();
+                self.link = Some((href, self.out.len()));
+            }
+            ("a", true) => {
+                if let Some((href, start)) = self.link.take() {
+                    let web = href.starts_with("http://") || href.starts_with("https://");
+                    if self.md && web && start < self.out.len() {
+                        let text = self.out.split_off(start);
+                        self.out.push_str(&format!("[{}]({})", text.trim(), href));
+                    }
+                }
+            }
+            ("img", _) if self.md => {
+                if let Some(src) = self.attr(tag, "src") {
+                    let alt = self.attr(tag, "alt").unwrap_or_default();
+                    self.out.push_str(&format!("![{}]({})", alt, src));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut text = String::with_capacity(self.out.len());
+        let mut blank = 0;
+        for line in self.out.lines() {
+            let line = line.trim_end();
+            if line.trim().is_empty() {
+                blank += 1;
+                if blank > 1 {
+                    continue;
+                }
+            } else {
+                blank = 0;
+            }
+            text.push_str(line);
+            text.push('\n');
+        }
+        text.trim().to_string()
+    }
+}
+
+// position of the `>` closing the tag at the start of s, `>` inside quoted attributes is skipped.
+// None when s does not start a tag: `<` not followed by a name, `/` or `!`, or no closing `>`
+fn tag_end(s: &str) -> Option<usize> {
+    match s[1..].chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '/' || c == '!' => {}
+        _ => return None,
+    }
+    let mut quote: Option<char> = None;
+    for (i, c) in s.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            (None, '<') => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// named entities common in dictionaries plus numeric `&#123;` / `&#x7b;`
+pub fn decode_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let end = match rest.find(';') {
+            Some(e) if e <= 10 => e,
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "middot" => Some('·'),
+            "hellip" => Some('…'),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            e if e.starts_with("#x") || e.starts_with("#X") => u32::from_str_radix(&e[2..], 16).ok().and_then(std::char::from_u32),
+            e if e.starts_with('#') => e[1..].parse::<u32>().ok().and_then(std::char::from_u32),
+            _ => None,
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_entities, Format, html_to_markdown, html_to_text};
+
+    #[test]
+    fn format_names() {
+        for (param, name) in [("html", "html"), ("TXT", "text"), ("text", "text"), ("markdown", "md"), ("md", "md")].iter() {
+            assert_eq!(Format::parse(param).map(|f| f.name()), Some(*name));
+        }
+        assert_eq!(Format::parse("pdf"), None);
+    }
+
+    #[test]
+    fn nested_lists() {
+        let html = "<ul><li>fruit<ol><li>apple</li><li>pear</li></ol></li><li>nut</li></ul>";
+        assert_eq!(html_to_text(html), "• fruit\n  1. apple\n  2. pear\n• nut");
+        assert_eq!(html_to_markdown(html), "- fruit\n  1. apple\n  2. pear\n- nut");
+    }
+
+    #[test]
+    fn ordered_list_start() {
+        assert_eq!(html_to_text("<ol start=\"3\"><li>c</li><li>d</li></ol>"), "3. c\n4. d");
+        assert_eq!(html_to_text("<ol start=x><li>a</li></ol>"), "1. a");
+    }
+
+    #[test]
+    fn bold_and_italic() {
+        let html = "<b>bank</b> <i>n.</i> <strong>river</strong> <em>side</em>";
+        assert_eq!(html_to_markdown(html), "**bank** _n._ **river** _side_");
+        assert_eq!(html_to_text(html), "bank n. river side");
+    }
+
+    #[test]
+    fn links() {
+        let html = "see <a href=\"https://example.com/?a=1&amp;b=2\">example</a> and <a href=\"entry://apple\">apple</a>";
+        assert_eq!(html_to_markdown(html), "see [example](https://example.com/?a=1&b=2) and apple");
+        assert_eq!(html_to_text(html), "see example and apple");
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(html_to_text("a&nbsp;&nbsp;b &amp; c&#233;&#x4e2d; &lt;d&gt;"), "a  b & cé中 <d>");
+        assert_eq!(decode_entities("&unknown; &#xzz; & &#65"), "&unknown; &#xzz; & &#65");
+        assert_eq!(decode_entities("&amp;lt;"), "&lt;");
+    }
+
+    #[test]
+    fn script_and_style_are_skipped() {
+        let html = "<head><title>t</title></head><style>.a { color: red }</style>word<script>if (a < b) x()</script> end";
+        assert_eq!(html_to_text(html), "word end");
+    }
+
+    #[test]
+    fn stray_less_than_is_text() {
+        assert_eq!(html_to_text("a < b <i>x</i>"), "a < b x");
+        assert_eq!(html_to_markdown("1<2 and <b>3</b> > 2"), "1<2 and **3** > 2");
+        assert_eq!(html_to_text("trailing <"), "trailing <");
+        assert_eq!(html_to_text("<b unclosed"), "<b unclosed");
+    }
+}
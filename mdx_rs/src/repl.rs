@@ -0,0 +1,177 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use mdx_rs::Dict;
+use mdx_rs::render::{Format, render};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use crate::cli::{Args, entry_title, Style};
+
+const HELP: &str = "type a word to look it up, tab completes headwords
+
+    :dicts              list loaded dictionaries
+    :use <id|number>    switch dictionary
+    :format text|md|html
+    :help
+    :quit               or ctrl-d";
+
+const COMMANDS: [&str; 5] = [":dicts", ":use ", ":format ", ":help", ":quit"];
+// completion stops after this many candidates, a one letter prefix matches thousands of words
+const MAX_CANDIDATES: usize = 100;
+
+/// completes `:commands` and headwords of the current dictionary
+struct ReplHelper {
+    // sorted headwords of the current dictionary
+    keys: Vec<String>,
+}
+
+impl ReplHelper {
+    fn sorted_keys(dict: &Dict) -> Vec<String> {
+        let mut keys: Vec<String> = dict.mdx.keys.iter().map(|k| k.key_text.clone()).collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let prefix = &line[..pos];
+        if prefix.starts_with(':') {
+            let cmds = COMMANDS.iter().filter(|c| c.starts_with(prefix)).map(|c| c.to_string()).collect();
+            return Ok((0, cmds));
+        }
+        if prefix.is_empty() {
+            return Ok((0, vec![]));
+        }
+        let start = match self.keys.binary_search_by(|k| k.as_str().cmp(prefix)) {
+            Ok(i) | Err(i) => i,
+        };
+        let candidates = self.keys[start..].iter()
+            .take_while(|k| k.starts_with(prefix))
+            .take(MAX_CANDIDATES)
+            .cloned()
+            .collect();
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+fn history_file() -> PathBuf {
+    let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    home.join(".mdx_history")
+}
+
+/// long definitions go through `$PAGER` (default `less -R`), short ones are printed
+fn page(text: &str) {
+    let rows = std::env::var("LINES").ok().and_then(|l| l.parse::<usize>().ok()).unwrap_or(24);
+    if text.lines().count() < rows {
+        println!("{}", text);
+        return;
+    }
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -R".to_string());
+    let mut parts = pager.split_whitespace();
+    let child = parts.next().and_then(|cmd| Command::new(cmd).args(parts).stdin(Stdio::piped()).spawn().ok());
+    match child {
+        Some(mut child) => {
+            if let Some(stdin) = child.stdin.as_mut() {
+                stdin.write_all(text.as_bytes()).ok();
+            }
+            child.wait().ok();
+        }
+        None => println!("{}", text),
+    }
+}
+
+/// `mdx repl a.mdx b.mdx ...`, interactive lookups until :quit or ctrl-d
+pub fn run(args: &Args, style: &Style) -> Result<i32, String> {
+    if args.positional.is_empty() {
+        return Err("repl needs at least one dictionary".to_string());
+    }
+    let dicts = args.positional.iter()
+        .map(|f| args.open_dict(f))
+        .collect::<Result<Vec<Dict>, String>>()?;
+    let mut format = args.format()?;
+    let mut current = 0;
+
+    let mut rl = Editor::<ReplHelper>::new();
+    rl.set_helper(Some(ReplHelper { keys: ReplHelper::sorted_keys(&dicts[current]) }));
+    let history = history_file();
+    rl.load_history(&history).ok();
+    println!("{}", style.dim(":help for commands"));
+
+    loop {
+        let prompt = format!("{}> ", dicts[current].id);
+        let line = match rl.readline(&prompt) {
+            Ok(line) => line.trim().to_string(),
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.to_string()),
+        };
+        if line.is_empty() {
+            continue;
+        }
+        rl.add_history_entry(line.as_str());
+
+        let (cmd, arg) = match line.find(' ') {
+            Some(i) if line.starts_with(':') => (&line[..i], line[i + 1..].trim()),
+            _ => (line.as_str(), ""),
+        };
+        match cmd {
+            ":quit" | ":q" => break,
+            ":help" => println!("{}", HELP),
+            ":dicts" => {
+                for (i, d) in dicts.iter().enumerate() {
+                    let mark = if i == current { "*" } else { " " };
+                    println!("{} {} {} ({} entries)", mark, i + 1, d.id, d.mdx.num_entries);
+                }
+            }
+            ":use" => {
+                let found = dicts.iter().position(|d| d.id == arg)
+                    .or_else(|| arg.parse::<usize>().ok().filter(|n| *n >= 1 && *n <= dicts.len()).map(|n| n - 1));
+                match found {
+                    Some(i) => {
+                        current = i;
+                        rl.helper_mut().unwrap().keys = ReplHelper::sorted_keys(&dicts[current]);
+                    }
+                    None => println!("{}", style.error(&format!("no dictionary {}", arg))),
+                }
+            }
+            ":format" => match Format::parse(arg) {
+                Some(f) => format = f,
+                None => println!("{}", style.error(&format!("unknown format: {}", arg))),
+            },
+            c if c.starts_with(':') => println!("{}", style.error(&format!("unknown command {}, :help for help", c))),
+            _ => {
+                let entries = dicts[current].lookup(&line);
+                if entries.is_empty() {
+                    println!("{}", style.error(&format!("{}: not found", line)));
+                    continue;
+                }
+                let text: Vec<String> = entries.iter().enumerate()
+                    .map(|(i, l)| format!("{}\n{}", entry_title(l, i, entries.len(), style), render(&l.definition, format)))
+                    .collect();
+                page(&text.join("\n\n"));
+            }
+        }
+    }
+    rl.save_history(&history).map_err(|e| e.to_string())?;
+    Ok(0)
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use crate::dict::LINK_PREFIX;
+use crate::mdx::Mdx;
+use crate::render::html_to_text;
+use crate::search::KeyPage;
+use crate::workers::Workers;
+
+/// inverted index from the words of definitions to the entries using them, for "which headwords
+/// mention this" queries. latin, cyrillic etc. are split into lowercased words, chinese, japanese
+/// and korean text has no spaces and is indexed as single characters and overlapping bigrams
+pub struct ReverseIndex {
+    /// headword of each indexed entry, in file order
+    headwords: Vec<String>,
+    /// token -> positions in `headwords`, ascending
+    postings: HashMap<String, Vec<u32>>,
+}
+
+impl ReverseIndex {
+    /// read every definition of the mdx, decompressing record blocks on `workers`
+    pub fn build(mdx: &Mdx, workers: &Workers) -> ReverseIndex {
+        let mut index = ReverseIndex { headwords: vec![], postings: HashMap::new() };
+        for (headword, definition) in mdx.par_entries(workers) {
+            // redirect records have no definition text of their own
+            if !definition.starts_with(LINK_PREFIX) {
+                index.add(headword, &html_to_text(&definition));
+            }
+        }
+        index
+    }
+
+    // the next entry and the text of its definition
+    fn add(&mut self, headword: String, text: &str) {
+        let id = self.headwords.len() as u32;
+        let mut tokens = tokenize(text);
+        tokens.sort();
+        tokens.dedup();
+        for token in tokens {
+            self.postings.entry(token).or_default().push(id);
+        }
+        self.headwords.push(headword);
+    }
+
+    /// number of indexed entries
+    pub fn len(&self) -> usize {
+        self.headwords.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headwords.is_empty()
+    }
+
+    /// headwords whose definition has every token of `query`, in file order, `limit` of them from
+    /// the `offset`th match. `银行` finds definitions with the bigram 银行, `bank account` those
+    /// with both words
+    pub fn search(&self, query: &str, offset: usize, limit: usize) -> KeyPage {
+        let mut tokens = tokenize(query);
+        tokens.sort();
+        tokens.dedup();
+        let mut lists = vec![];
+        for token in &tokens {
+            match self.postings.get(token) {
+                Some(list) => lists.push(list),
+                None => return KeyPage { keys: vec![], offset, total: 0 },
+            }
+        }
+        // intersect from the rarest token, each check is a binary search in the longer lists
+        lists.sort_by_key(|l| l.len());
+        let matches: Vec<u32> = match lists.split_first() {
+            Some((rarest, rest)) => rarest.iter()
+                .filter(|id| rest.iter().all(|l| l.binary_search(id).is_ok()))
+                .cloned()
+                .collect(),
+            None => vec![],
+        };
+        KeyPage {
+            keys: matches.iter().skip(offset).take(limit).map(|&id| self.headwords[id as usize].clone()).collect(),
+            offset,
+            total: matches.len(),
+        }
+    }
+}
+
+/// lowercased words of non-cjk text, single characters and bigrams of cjk runs
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut word = String::new();
+    let mut prev_cjk: Option<char> = None;
+    for c in text.chars() {
+        if is_cjk(c) {
+            flush(&mut word, &mut tokens);
+            tokens.push(c.to_string());
+            if let Some(p) = prev_cjk {
+                tokens.push([p, c].iter().collect());
+            }
+            prev_cjk = Some(c);
+            continue;
+        }
+        prev_cjk = None;
+        if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        } else {
+            flush(&mut word, &mut tokens);
+        }
+    }
+    flush(&mut word, &mut tokens);
+    tokens
+}
+
+fn flush(word: &mut String, tokens: &mut Vec<String>) {
+    if !word.is_empty() {
+        tokens.push(std::mem::take(word));
+    }
+}
+
+// han ideographs, kana and hangul syllables
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30ff | 0x3400..=0x4dbf | 0x4e00..=0x9fff | 0xac00..=0xd7af | 0xf900..=0xfaff | 0x20000..=0x2ffff)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{tokenize, ReverseIndex};
+
+    fn index(entries: &[(&str, &str)]) -> ReverseIndex {
+        let mut index = ReverseIndex { headwords: vec![], postings: HashMap::new() };
+        for (headword, text) in entries {
+            index.add(headword.to_string(), text);
+        }
+        index
+    }
+
+    #[test]
+    fn words_are_lowercased() {
+        assert_eq!(tokenize("A river-Bank, 2 banks!"), ["a", "river", "bank", "2", "banks"]);
+        assert_eq!(tokenize("Éclair über"), ["éclair", "über"]);
+        assert!(tokenize(" ,.; ").is_empty());
+    }
+
+    #[test]
+    fn cjk_unigrams_and_bigrams() {
+        assert_eq!(tokenize("银行"), ["银", "行", "银行"]);
+        assert_eq!(tokenize("中国银行"), ["中", "国", "中国", "银", "国银", "行", "银行"]);
+        assert_eq!(tokenize("かな"), ["か", "な", "かな"]);
+    }
+
+    #[test]
+    fn mixed_scripts() {
+        // a bigram never spans a non-cjk character
+        assert_eq!(tokenize("ATM机取款"), ["atm", "机", "取", "机取", "款", "取款"]);
+        assert_eq!(tokenize("河岸 bank 银行"), ["河", "岸", "河岸", "bank", "银", "行", "银行"]);
+    }
+
+    #[test]
+    fn search_intersects_tokens() {
+        let index = index(&[
+            ("bank", "a financial institution 银行"),
+            ("shore", "the bank of a river 河岸"),
+            ("teller", "a person who works in a bank 银行职员"),
+        ]);
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.search("bank", 0, 10).keys, ["shore", "teller"]);
+        assert_eq!(index.search("Bank River", 0, 10).keys, ["shore"]);
+        assert_eq!(index.search("银行", 0, 10).keys, ["bank", "teller"]);
+        assert_eq!(index.search("行职", 0, 10).keys, ["teller"]);
+        assert_eq!(index.search("bank 银行", 0, 10).keys, ["teller"]);
+        assert_eq!(index.search("bank nothing", 0, 10).total, 0);
+        assert_eq!(index.search("", 0, 10).total, 0);
+    }
+
+    #[test]
+    fn search_pages() {
+        let entries: Vec<(String, String)> = (0..25).map(|i| (format!("w{}", i), format!("common {}", i))).collect();
+        let entries: Vec<(&str, &str)> = entries.iter().map(|(h, t)| (h.as_str(), t.as_str())).collect();
+        let index = index(&entries);
+        let page = index.search("common", 10, 5);
+        assert_eq!((page.offset, page.total), (10, 25));
+        assert_eq!(page.keys, ["w10", "w11", "w12", "w13", "w14"]);
+        assert_eq!(index.search("common", 23, 5).keys, ["w23", "w24"]);
+        assert!(index.search("common", 30, 5).keys.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use regex::{Regex, RegexBuilder};
+use serde_derive::Serialize;
+
+use crate::error::{MdxError, Result};
+use crate::mdx::{KeyIndex, Mdx};
+
+// compiled size limit, a pattern from a url like `(a{100}){100}` fails instead of eating memory
+const MAX_REGEX_SIZE: usize = 1 << 20;
+
+/// headword pattern of `mdx keys` and `/keys`. a regex when asked for, shell wildcards (`*` any
+/// characters, `?` one character) matched against the whole headword when the pattern has them,
+/// otherwise a substring. case is ignored unless the dictionary is KeyCaseSensitive
+pub struct KeyPattern {
+    regex: Regex,
+}
+
+impl KeyPattern {
+    pub fn parse(pattern: &str, regex: bool, case_sensitive: bool) -> Result<KeyPattern> {
+        let source = if regex {
+            pattern.to_string()
+        } else if pattern.contains(&['*', '?'][..]) {
+            wildcard_regex(pattern)
+        } else {
+            regex::escape(pattern)
+        };
+        RegexBuilder::new(&source)
+            .case_insensitive(!case_sensitive)
+            .size_limit(MAX_REGEX_SIZE)
+            .build()
+            .map(|regex| KeyPattern { regex })
+            .map_err(|e| MdxError::Invalid(format!("pattern {:?}: {}", pattern, e)))
+    }
+
+    pub fn is_match(&self, key: &str) -> bool {
+        self.regex.is_match(key)
+    }
+}
+
+// `re?d*` -> `^re.d.*$`, everything but the wildcards is literal
+fn wildcard_regex(pattern: &str) -> String {
+    let mut source = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => source.push_str(".*"),
+            '?' => source.push('.'),
+            c => source.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    source.push('$');
+    source
+}
+
+/// one page of matching headwords, `total` counts every match
+#[derive(Debug, Serialize)]
+pub struct KeyPage {
+    pub keys: Vec<String>,
+    pub offset: usize,
+    pub total: usize,
+}
+
+impl Mdx {
+    /// keys whose headword matches `pattern`, in file order
+    pub fn matching_keys<'a>(&'a self, pattern: &'a KeyPattern) -> impl Iterator<Item=&'a KeyIndex> + 'a {
+        self.keys.iter().filter(move |k| pattern.is_match(&k.key_text))
+    }
+
+    /// at most `limit` matching headwords starting at the `offset`th match
+    pub fn search_keys(&self, pattern: &KeyPattern, offset: usize, limit: usize) -> KeyPage {
+        let mut keys = vec![];
+        let mut total = 0;
+        for k in self.matching_keys(pattern) {
+            if total >= offset && keys.len() < limit {
+                keys.push(k.key_text.clone());
+            }
+            total += 1;
+        }
+        KeyPage { keys, offset, total }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wildcard_regex, KeyPattern};
+
+    #[test]
+    fn wildcards() {
+        assert_eq!(wildcard_regex("*tion"), "^.*tion$");
+        assert_eq!(wildcard_regex("re?d"), "^re.d$");
+        assert_eq!(wildcard_regex("a.b*"), r"^a\.b.*$");
+        let p = KeyPattern::parse("*tion", false, false).unwrap();
+        assert!(p.is_match("nation") && p.is_match("tion"));
+        assert!(!p.is_match("nationality"));
+        let p = KeyPattern::parse("re?d", false, false).unwrap();
+        assert!(p.is_match("read") && p.is_match("reed"));
+        assert!(!p.is_match("red") && !p.is_match("reads"));
+    }
+
+    #[test]
+    fn substring_is_literal() {
+        let p = KeyPattern::parse("a.b", false, false).unwrap();
+        assert!(p.is_match("xa.by"));
+        assert!(!p.is_match("axb"));
+        let p = KeyPattern::parse("c++ (lang)", false, false).unwrap();
+        assert!(p.is_match("C++ (lang) notes"));
+        assert!(KeyPattern::parse("[", false, false).unwrap().is_match("a[1]"));
+    }
+
+    #[test]
+    fn regex_mode() {
+        let p = KeyPattern::parse("^ab(c|d)$", true, false).unwrap();
+        assert!(p.is_match("abc") && p.is_match("ABD"));
+        assert!(!p.is_match("abcd"));
+        assert!(KeyPattern::parse("(", true, false).is_err());
+        assert!(KeyPattern::parse("(a{100}){100}{100}", true, false).is_err());
+    }
+
+    #[test]
+    fn case_folding() {
+        assert!(KeyPattern::parse("ABC", false, false).unwrap().is_match("abc"));
+        assert!(KeyPattern::parse("*Tion", false, false).unwrap().is_match("NATION"));
+        assert!(!KeyPattern::parse("ABC", false, true).unwrap().is_match("abc"));
+        assert!(!KeyPattern::parse("A?C", false, true).unwrap().is_match("abc"));
+    }
+}
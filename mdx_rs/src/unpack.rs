@@ -0,0 +1,29 @@
+/// byte order of a number in the file. counts and sizes are big endian, the adler32 after the
+/// header text is little endian
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endian {
+    BE,
+    LE,
+}
+
+/// unsigned number stored in `bytes`, any width from 0 to 8 bytes. v1 files use 4 byte numbers
+/// and 1 byte text sizes where v2 uses 8 and 2, so the width is the length of the slice
+pub fn unpack_u64(bytes: &[u8], endian: Endian) -> u64 {
+    debug_assert!(bytes.len() <= 8, "{} bytes do not fit in a u64", bytes.len());
+    let fold = |n: u64, b: &u8| n << 8 | *b as u64;
+    match endian {
+        Endian::BE => bytes.iter().fold(0, fold),
+        Endian::LE => bytes.iter().rev().fold(0, fold),
+    }
+}
+
+/// `unpack_u64` for numbers the format keeps below 2^32, adler32 checksums and v1 sizes
+pub fn unpack_u32(bytes: &[u8], endian: Endian) -> u32 {
+    unpack_u64(bytes, endian) as u32
+}
+
+/// decode utf-16le text, None on an unpaired surrogate. a trailing odd byte is ignored
+pub fn utf16_le_string(bytes: &[u8]) -> Option<String> {
+    let units = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]]));
+    std::char::decode_utf16(units).collect::<Result<String, _>>().ok()
+}
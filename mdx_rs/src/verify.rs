@@ -0,0 +1,258 @@
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+
+use crate::checksum::adler32_checksum;
+use crate::cursor::Cursor;
+use crate::mdx::{decrypt_key_block_info, extract_header, HeaderBuilder, inflate, KeyIndex, MAX_BLOCK_SIZE, Mdx, parse_key_block_info, split_key_block};
+use crate::unpack::{Endian, utf16_le_string};
+
+/// part of the file a problem was found in, blocks are numbered from 0
+#[derive(Debug, Clone, PartialEq)]
+pub enum Section {
+    File,
+    Header,
+    KeyBlockInfo,
+    KeyBlock(usize),
+    RecordBlockInfo,
+    RecordBlock(usize),
+    Entries,
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Section::File => write!(f, "file"),
+            Section::Header => write!(f, "header"),
+            Section::KeyBlockInfo => write!(f, "key block info"),
+            Section::KeyBlock(i) => write!(f, "key block #{}", i),
+            Section::RecordBlockInfo => write!(f, "record block info"),
+            Section::RecordBlock(i) => write!(f, "record block #{}", i),
+            Section::Entries => write!(f, "entries"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub section: Section,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.section, self.message)
+    }
+}
+
+/// result of `Mdx::verify`, every problem found in the file
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub file: String,
+    pub key_blocks_checked: usize,
+    pub record_blocks_checked: usize,
+    pub entries: u64,
+    pub problems: Vec<Problem>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    fn problem(&mut self, section: Section, message: String) {
+        self.problems.push(Problem { section, message });
+    }
+}
+
+impl Mdx {
+    /// check the header, key block info, every key block, the record block info, every record block
+    /// and the entry counts. unlike `Mdx::open` it does not stop at the first error,
+    /// only a truncated or unreadable file ends the check early
+    pub fn verify(file: &str) -> VerifyReport {
+        let mut report = VerifyReport {
+            file: file.to_string(),
+            ..VerifyReport::default()
+        };
+        if let Err(p) = verify_file(file, &mut report) {
+            report.problems.push(p);
+        }
+        report
+    }
+}
+
+fn fatal(section: Section, message: String) -> Problem {
+    Problem { section, message }
+}
+
+fn read(input: &mut Cursor<BufReader<File>>, n: u64, section: Section) -> Result<Vec<u8>, Problem> {
+    input.read(n, "file").map_err(|e| fatal(section, e.to_string()))
+}
+
+// every complete `width` byte number in bytes
+fn numbers(bytes: &[u8], width: usize) -> Vec<u64> {
+    let mut cursor = Cursor::new(bytes).width(width);
+    std::iter::from_fn(|| cursor.number("numbers").ok()).collect()
+}
+
+/// type + adler32 + payload block used by key blocks and record blocks, returns the decompressed bytes
+fn check_block(block: &[u8], expected_size: u64, section: Section, report: &mut VerifyReport) -> Option<Vec<u8>> {
+    if block.len() < 8 {
+        report.problem(section, format!("block is only {} bytes", block.len()));
+        return None;
+    }
+    let data = match &block[0..4] {
+        b"\x00\x00\x00\x00" => block[8..].to_vec(),
+        // a block decompressing past its declared size is reported as too large
+        b"\x02\x00\x00\x00" => match inflate(&block[8..], (expected_size as usize).min(MAX_BLOCK_SIZE), "block") {
+            Ok(d) => d,
+            Err(e) => {
+                report.problem(section, e.to_string());
+                return None;
+            }
+        },
+        b"\x01\x00\x00\x00" => {
+            report.problem(section, "lzo compression is not supported, block not checked".to_string());
+            return None;
+        }
+        t => {
+            report.problem(section, format!("unknown block type {:?}", t));
+            return None;
+        }
+    };
+    if !adler32_checksum(&data, &block[4..8], Endian::BE) {
+        report.problem(section.clone(), "adler32 checksum mismatch".to_string());
+    }
+    if data.len() as u64 != expected_size {
+        report.problem(section, format!("decompressed to {} bytes, block info says {}", data.len(), expected_size));
+    }
+    Some(data)
+}
+
+fn verify_file(file: &str, report: &mut VerifyReport) -> Result<(), Problem> {
+    let f = File::open(file).map_err(|e| fatal(Section::File, e.to_string()))?;
+    let len = f.metadata().map_err(|e| fatal(Section::File, e.to_string()))?.len();
+    let mut input = Cursor::with_len(BufReader::new(f), len);
+
+    // header
+    let header_len = input.u32(Endian::BE, "header length").map_err(|e| fatal(Section::Header, e.to_string()))?;
+    let header_bytes = read(&mut input, header_len as u64, Section::Header)?;
+    let adler32_bytes = read(&mut input, 4, Section::Header)?;
+    if !adler32_checksum(&header_bytes, &adler32_bytes, Endian::LE) {
+        report.problem(Section::Header, "adler32 checksum mismatch".to_string());
+    }
+    if header_bytes.len() < 2 {
+        return Err(fatal(Section::Header, "header is empty".to_string()));
+    }
+    let header_txt = utf16_le_string(&header_bytes[..header_bytes.len() - 2])
+        .ok_or_else(|| fatal(Section::Header, "header is not utf-16 text".to_string()))?;
+    let mut hb = HeaderBuilder::default();
+    hb.file(file.to_string());
+    extract_header(&mut hb, header_txt).map_err(|e| fatal(Section::Header, e.to_string()))?;
+    if file.to_lowercase().ends_with(".mdd") {
+        hb.encoding("UTF-16".to_string());
+    }
+    let header = hb.build();
+    let v2 = header.genversion >= 2.0;
+    let width = if v2 { 8 } else { 4 };
+
+    // key block info meta: num_key_blocks, num_entries, [key_block_info_decompressed_size], key_block_info_size, key_block_size
+    let meta = read(&mut input, if v2 { 8 * 5 } else { 4 * 4 }, Section::KeyBlockInfo)?;
+    let meta_numbers = numbers(&meta, width);
+    let (num_key_blocks, num_entries, key_block_info_size, key_block_size) =
+        (meta_numbers[0], meta_numbers[1], meta_numbers[meta_numbers.len() - 2], meta_numbers[meta_numbers.len() - 1]);
+    report.entries = num_entries;
+    if v2 {
+        let adler32_bytes = read(&mut input, 4, Section::KeyBlockInfo)?;
+        if !adler32_checksum(&meta, &adler32_bytes, Endian::BE) {
+            report.problem(Section::KeyBlockInfo, "adler32 checksum of the size fields mismatch".to_string());
+        }
+    }
+    let key_block_info_compressed = read(&mut input, key_block_info_size, Section::KeyBlockInfo)?;
+    let key_block_bytes = read(&mut input, key_block_size, Section::KeyBlock(0))?;
+
+    let key_block_info_bytes = if v2 {
+        if key_block_info_compressed.len() < 8 || &key_block_info_compressed[0..4] != b"\x02\x00\x00\x00" {
+            return Err(fatal(Section::KeyBlockInfo, "not a zlib compressed block".to_string()));
+        }
+        let decrypted = decrypt_key_block_info(&key_block_info_compressed, &header)
+            .map_err(|e| fatal(Section::KeyBlockInfo, e.to_string()))?;
+        let bytes = inflate(&decrypted, MAX_BLOCK_SIZE, "key block info").map_err(|e| fatal(Section::KeyBlockInfo, e.to_string()))?;
+        if !adler32_checksum(&bytes, &key_block_info_compressed[4..8], Endian::BE) {
+            report.problem(Section::KeyBlockInfo, "adler32 checksum mismatch".to_string());
+        }
+        if bytes.len() as u64 != meta_numbers[2] {
+            report.problem(Section::KeyBlockInfo, format!("decompressed to {} bytes, header says {}", bytes.len(), meta_numbers[2]));
+        }
+        bytes
+    } else {
+        key_block_info_compressed
+    };
+    let key_block_info_list = parse_key_block_info(&key_block_info_bytes, &header)
+        .map_err(|e| fatal(Section::KeyBlockInfo, e.to_string()))?;
+    if key_block_info_list.len() as u64 != num_key_blocks {
+        report.problem(Section::KeyBlockInfo, format!("lists {} key blocks, header says {}", key_block_info_list.len(), num_key_blocks));
+    }
+    let listed_size = key_block_info_list.iter().fold(0usize, |sum, b| sum.saturating_add(b.compressed_size));
+    if listed_size as u64 != key_block_size {
+        report.problem(Section::KeyBlockInfo, format!("key blocks sum to {} bytes, header says {}", listed_size, key_block_size));
+    }
+    let listed_entries = key_block_info_list.iter().fold(0u64, |sum, b| sum.saturating_add(b.num_entries));
+    if listed_entries != num_entries {
+        report.problem(Section::KeyBlockInfo, format!("lists {} entries, header says {}", listed_entries, num_entries));
+    }
+
+    // key blocks
+    let mut keys: Vec<KeyIndex> = vec![];
+    let mut start = 0;
+    for (i, info) in key_block_info_list.iter().enumerate() {
+        let c_size = info.compressed_size;
+        if c_size > key_block_bytes.len() - start {
+            report.problem(Section::KeyBlock(i), "block extends past the key block section".to_string());
+            break;
+        }
+        if let Some(block) = check_block(&key_block_bytes[start..start + c_size], info.decompressed_size as u64, Section::KeyBlock(i), report) {
+            let before = keys.len();
+            match split_key_block(&block, &mut keys, header.is_utf16(), width) {
+                Ok(()) if (keys.len() - before) as u64 != info.num_entries => report.problem(Section::KeyBlock(i),
+                    format!("has {} keys, key block info says {}", keys.len() - before, info.num_entries)),
+                Ok(()) => {}
+                Err(e) => report.problem(Section::KeyBlock(i), e.to_string()),
+            }
+        }
+        report.key_blocks_checked += 1;
+        start += c_size;
+    }
+    if keys.len() as u64 != num_entries {
+        report.problem(Section::Entries, format!("key blocks have {} keys, header says {}", keys.len(), num_entries));
+    }
+
+    // record block info
+    let record_meta = numbers(&read(&mut input, 4 * width as u64, Section::RecordBlockInfo)?, width);
+    let (num_record_blocks, record_entries, record_block_info_size, record_block_size) =
+        (record_meta[0], record_meta[1], record_meta[2], record_meta[3]);
+    if record_entries != num_entries {
+        report.problem(Section::Entries, format!("record section has {} entries, key section has {}", record_entries, num_entries));
+    }
+    let info_size = num_record_blocks.saturating_mul(width as u64 * 2);
+    if record_block_info_size != info_size {
+        report.problem(Section::RecordBlockInfo,
+                       format!("size is {} bytes, {} blocks need {}", record_block_info_size, num_record_blocks, info_size));
+    }
+    let sizes = numbers(&read(&mut input, info_size, Section::RecordBlockInfo)?, width);
+    let listed_size = sizes.iter().step_by(2).fold(0u64, |sum, c| sum.saturating_add(*c));
+    if listed_size != record_block_size {
+        report.problem(Section::RecordBlockInfo, format!("record blocks sum to {} bytes, header says {}", listed_size, record_block_size));
+    }
+
+    // record blocks
+    for (i, pair) in sizes.chunks(2).enumerate() {
+        let block = read(&mut input, pair[0], Section::RecordBlock(i))?;
+        check_block(&block, pair[1], Section::RecordBlock(i), report);
+        report.record_blocks_checked += 1;
+    }
+    if !input.is_empty() {
+        report.problem(Section::File, format!("{} unexpected bytes after the last record block", input.remaining()));
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use std::sync::{mpsc, Mutex};
+
+use futures_channel::oneshot;
+use lazy_static::lazy_static;
+use threadpool::ThreadPool;
+
+use crate::error::{MdxError, Result};
+
+lazy_static! {
+    // threads of `offload`, apart from the block decoding pools: a lookup on a pool thread may
+    // hand its blocks to a Workers pool, one shared pool could end up with every thread waiting
+    static ref OFFLOAD: Mutex<ThreadPool> = {
+        let count = std::env::var("MDX_LOOKUP_THREADS").ok()
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or_else(|| num_cpus::get() * 4);
+        Mutex::new(ThreadPool::with_name("mdx-lookup".to_string(), count.max(1)))
+    };
+}
+
+/// `f` run on a dedicated thread pool, for async servers: lookups query sqlite, read the mdx file and
+/// inflate a record block, none of that should hold up the executor. the future works with any
+/// executor. `MDX_LOOKUP_THREADS` sets the number of threads, default four per cpu as the threads
+/// mostly wait on the disk. a panic in `f` is a `MdxError::Panicked` named by `what`
+pub async fn offload<T, F>(what: &str, f: F) -> Result<T>
+    where F: FnOnce() -> T + Send + 'static, T: Send + 'static {
+    let (tx, rx) = oneshot::channel();
+    OFFLOAD.lock().unwrap().execute(move || {
+        tx.send(f()).ok();
+    });
+    // the sender is dropped without a value when `f` panics
+    rx.await.map_err(|_| MdxError::Panicked(what.to_string()))
+}
+
+/// thread pool for decoding key and record blocks, they are independent zlib streams.
+/// `MDX_WORKERS` sets the number of threads, default one per cpu, 1 decodes on the calling thread
+pub struct Workers {
+    pool: Option<ThreadPool>,
+    count: usize,
+}
+
+impl Workers {
+    pub fn new(count: usize) -> Workers {
+        let count = count.max(1);
+        let pool = if count > 1 { Some(ThreadPool::new(count)) } else { None };
+        Workers { pool, count }
+    }
+
+    pub fn from_env() -> Workers {
+        let count = std::env::var("MDX_WORKERS").ok()
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or_else(num_cpus::get);
+        Workers::new(count)
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// blocks read from the file before handing them to the pool, bounds the memory held at once
+    pub fn batch_size(&self) -> usize {
+        self.count * 4
+    }
+
+    /// `f` applied to every item, results are in the order of items.
+    /// a panic in `f` (e.g. a checksum error) is raised again on the calling thread
+    pub fn map<T, R>(&self, items: Vec<T>, f: fn(T) -> R) -> Vec<R>
+        where T: Send + 'static, R: Send + 'static {
+        let pool = match &self.pool {
+            Some(pool) if items.len() > 1 => pool,
+            _ => return items.into_iter().map(f).collect(),
+        };
+        let n = items.len();
+        let (tx, rx) = mpsc::channel();
+        for (i, item) in items.into_iter().enumerate() {
+            let tx = tx.clone();
+            pool.execute(move || {
+                tx.send((i, f(item))).ok();
+            });
+        }
+        drop(tx);
+        let mut results: Vec<Option<R>> = (0..n).map(|_| None).collect();
+        for (i, r) in rx {
+            results[i] = Some(r);
+        }
+        results.into_iter()
+            .map(|r| r.unwrap_or_else(|| panic!("block decoding failed in a worker thread")))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_executor::block_on;
+
+    use super::offload;
+    use crate::error::MdxError;
+
+    #[test]
+    fn offload_results() {
+        assert_eq!(block_on(offload("sum", || (1..=10).sum::<i32>())).unwrap(), 55);
+        match block_on(offload("bad block", || -> i32 { panic!("checksum") })) {
+            Err(MdxError::Panicked(what)) => assert_eq!(what, "bad block"),
+            _ => panic!("the panic is not an error"),
+        }
+        // the pool replaces the thread that panicked
+        assert_eq!(block_on(offload("after", || 1)).unwrap(), 1);
+    }
+}
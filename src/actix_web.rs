@@ -2,6 +2,7 @@
 extern crate actix_web;
 
 use std::{env, io};
+use std::sync::Arc;
 
 use actix_files as fs;
 use actix_session::{CookieSession, Session};
@@ -12,7 +13,12 @@ use actix_web::{
     Result,
 };
 use bytes::Bytes;
+use mdx_rs::{Dict, Lemmatizer};
+use mdx_rs::render::{Format, join_sections, render};
 
+use crate::history::History;
+
+mod history;
 
 /// favicon handler
 #[get("/favicon")]
@@ -41,6 +47,57 @@ async fn welcome(session: Session, req: HttpRequest) -> Result<HttpResponse> {
         .body(include_str!("../static/welcome.html")))
 }
 
+/// dictionaries for `/dict/{word}`, `MDX_PATHS=a.mdx:b.mdx`. `MDX_LEMMAS=all` or a comma separated
+/// list of dictionary ids turns on base form lookups of inflected english words, as in mdx-server
+fn load_dicts() -> Vec<Arc<Dict>> {
+    let paths = env::var("MDX_PATHS").unwrap_or_else(|_| "resources/葛传椝英语惯用法词典.mdx".to_string());
+    let lemmas = env::var("MDX_LEMMAS").unwrap_or_default();
+    let lemmatizer = Arc::new(Lemmatizer::english());
+    paths.split(':').filter(|p| !p.is_empty()).filter_map(|p| match Dict::open(p) {
+        Ok(mut d) => {
+            if lemmas.split(',').map(|id| id.trim()).any(|id| id == "all" || id == d.id) {
+                d.lemmatizer = Some(lemmatizer.clone());
+            }
+            Some(Arc::new(d))
+        }
+        Err(e) => {
+            log::error!("skipping {}: {}", p, e);
+            None
+        }
+    }).collect()
+}
+
+/// every entry of a word from the first dictionary that has it, homographs as separate sections.
+/// found words are added to the session's history
+#[get("/dict/{word}")]
+async fn dict_lookup(session: Session, dicts: web::Data<Vec<Arc<Dict>>>, history: web::Data<History>,
+                     path: web::Path<String>) -> Result<HttpResponse> {
+    let word = path.into_inner();
+    let uid = history::user_id(&session)?;
+    // the records are read from disk and inflated on mdx_rs's lookup pool, off the event loop
+    let mut found = None;
+    for dict in dicts.iter() {
+        let entries = dict.clone().lookup_async(word.clone()).await.map_err(error::ErrorInternalServerError)?;
+        if !entries.is_empty() {
+            let definitions: Vec<String> = entries.iter().map(|l| render(&l.definition, Format::Html)).collect();
+            found = Some(join_sections(&definitions, Format::Html));
+            break;
+        }
+    }
+    let definition = match found {
+        Some(definition) => definition,
+        None => return Ok(HttpResponse::NotFound().body("not found")),
+    };
+    // the lookup is still answered when redis is down
+    let who = uid.clone();
+    if let Err(e) = web::block(move || history.record(&uid, &word)).await {
+        log::warn!("history of {}: {}", who, e);
+    }
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(definition))
+}
+
 /// 404 handler
 async fn p404() -> Result<fs::NamedFile> {
     Ok(fs::NamedFile::open("static/404.html")?.set_status_code(StatusCode::NOT_FOUND))
@@ -65,21 +122,42 @@ async fn with_param(req: HttpRequest, path: web::Path<(String, )>) -> HttpRespon
         .body(format!("Hello {}!", path.0))
 }
 
+/// `SESSION_KEY` signs the session cookie that holds the history user id, at least 32 bytes.
+/// a key in the source would let anyone forge a cookie and read another session's history
+fn session_key() -> io::Result<Vec<u8>> {
+    let key = env::var("SESSION_KEY")
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "SESSION_KEY is not set"))?;
+    if key.len() < 32 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "SESSION_KEY must be at least 32 bytes"));
+    }
+    Ok(key.into_bytes())
+}
+
 #[actix_rt::main]
 async fn main() -> io::Result<()> {
     env::set_var("RUST_LOG", "actix_web=debug,actix_server=info");
     env_logger::init();
+    let key = session_key()?;
+    let dicts = web::Data::new(load_dicts());
+    let history = web::Data::new(History::from_env()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("REDIS_URL: {}", e)))?);
 
-    HttpServer::new(|| {
+    HttpServer::new(move || {
         App::new()
+            .app_data(dicts.clone())
+            .app_data(history.clone())
             // cookie session middleware
-            .wrap(CookieSession::signed(&[0; 32]).secure(false))
+            .wrap(CookieSession::signed(&key).secure(false))
             // enable logger - always register actix-web Logger middleware last
             .wrap(middleware::Logger::default())
             // register favicon
             .service(favicon)
             // register simple route, handle all methods
             .service(welcome)
+            // dictionary lookup
+            .service(dict_lookup)
+            // lookup history and starred words of the session
+            .configure(history::routes)
             // with path parameters
             .service(web::resource("/user/{name}").route(web::get().to(with_param)))
             // async response body
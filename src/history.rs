@@ -0,0 +1,217 @@
+//! lookup history and starred words of each browser session, kept in redis under a random user id
+//! stored in the session cookie
+
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_session::Session;
+use actix_web::{error, web, HttpResponse, Result};
+use redis::{Client, RedisResult};
+use serde::Serialize;
+
+// newest lookups kept per user
+const MAX_HISTORY: isize = 500;
+// redis drops the keys of a user who has not looked anything up for 90 days
+const TTL_SECS: usize = 90 * 24 * 3600;
+
+/// a looked up or starred word and when, unix seconds
+#[derive(Serialize)]
+pub struct Item {
+    pub word: String,
+    pub at: u64,
+}
+
+/// per user history and favourites, `REDIS_URL` or redis on localhost
+pub struct History {
+    client: Client,
+}
+
+impl History {
+    pub fn from_env() -> RedisResult<History> {
+        let url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        Ok(History { client: Client::open(url.as_str())? })
+    }
+
+    /// add a lookup, the list is trimmed to MAX_HISTORY
+    pub fn record(&self, uid: &str, word: &str) -> RedisResult<()> {
+        let key = history_key(uid);
+        let mut conn = self.client.get_connection()?;
+        redis::pipe()
+            .cmd("LPUSH").arg(&key).arg(format!("{}\t{}", now(), word)).ignore()
+            .cmd("LTRIM").arg(&key).arg(0).arg(MAX_HISTORY - 1).ignore()
+            .cmd("EXPIRE").arg(&key).arg(TTL_SECS).ignore()
+            .query(&mut conn)
+    }
+
+    /// lookups, newest first
+    pub fn history(&self, uid: &str) -> RedisResult<Vec<Item>> {
+        let mut conn = self.client.get_connection()?;
+        let entries: Vec<String> = redis::cmd("LRANGE").arg(history_key(uid)).arg(0).arg(-1).query(&mut conn)?;
+        Ok(entries.iter().filter_map(|e| {
+            let (at, word) = e.split_at(e.find('\t')?);
+            Some(Item { word: word[1..].to_string(), at: at.parse().ok()? })
+        }).collect())
+    }
+
+    pub fn clear_history(&self, uid: &str) -> RedisResult<()> {
+        let mut conn = self.client.get_connection()?;
+        redis::cmd("DEL").arg(history_key(uid)).query(&mut conn)
+    }
+
+    /// star a word, starring it again moves it to the top
+    pub fn star(&self, uid: &str, word: &str) -> RedisResult<()> {
+        let key = stars_key(uid);
+        let mut conn = self.client.get_connection()?;
+        redis::pipe()
+            .cmd("ZADD").arg(&key).arg(now()).arg(word).ignore()
+            .cmd("EXPIRE").arg(&key).arg(TTL_SECS).ignore()
+            .query(&mut conn)
+    }
+
+    pub fn unstar(&self, uid: &str, word: &str) -> RedisResult<()> {
+        let mut conn = self.client.get_connection()?;
+        redis::cmd("ZREM").arg(stars_key(uid)).arg(word).query(&mut conn)
+    }
+
+    /// starred words, newest first
+    pub fn stars(&self, uid: &str) -> RedisResult<Vec<Item>> {
+        let mut conn = self.client.get_connection()?;
+        let stars: Vec<(String, f64)> = redis::cmd("ZREVRANGE").arg(stars_key(uid)).arg(0).arg(-1).arg("WITHSCORES")
+            .query(&mut conn)?;
+        Ok(stars.into_iter().map(|(word, at)| Item { word, at: at as u64 }).collect())
+    }
+
+    pub fn clear_stars(&self, uid: &str) -> RedisResult<()> {
+        let mut conn = self.client.get_connection()?;
+        redis::cmd("DEL").arg(stars_key(uid)).query(&mut conn)
+    }
+}
+
+fn history_key(uid: &str) -> String {
+    format!("mdx:history:{}", uid)
+}
+
+fn stars_key(uid: &str) -> String {
+    format!("mdx:stars:{}", uid)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// user id of the session, a new one is set in the cookie on the first request
+pub fn user_id(session: &Session) -> Result<String> {
+    if let Some(uid) = session.get::<String>("uid")? {
+        return Ok(uid);
+    }
+    let uid = format!("{:032x}", rand::random::<u128>());
+    session.set("uid", &uid)?;
+    Ok(uid)
+}
+
+// run a store call on the blocking pool, redis being down is a 503
+async fn store<T, F>(history: web::Data<History>, f: F) -> Result<T>
+    where F: FnOnce(&History) -> RedisResult<T> + Send + 'static, T: Send + 'static {
+    web::block(move || f(&history)).await.map_err(|e| {
+        log::error!("history store: {}", e);
+        error::ErrorServiceUnavailable("history store unavailable")
+    })
+}
+
+// word,unix seconds per line, words with commas or quotes are quoted
+fn csv(items: &[Item], time_column: &str) -> String {
+    let mut out = format!("word,{}\n", time_column);
+    for item in items {
+        if item.word.contains(&[',', '"', '\n'][..]) {
+            out.push_str(&format!("\"{}\",{}\n", item.word.replace('"', "\"\""), item.at));
+        } else {
+            out.push_str(&format!("{},{}\n", item.word, item.at));
+        }
+    }
+    out
+}
+
+fn csv_download(body: String, filename: &str) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .header("content-disposition", format!("attachment; filename=\"{}\"", filename))
+        .body(body)
+}
+
+/// GET /history, this session's lookups as json, newest first
+#[get("/history")]
+async fn history_list(session: Session, history: web::Data<History>) -> Result<HttpResponse> {
+    let uid = user_id(&session)?;
+    let items = store(history, move |h| h.history(&uid)).await?;
+    Ok(HttpResponse::Ok().json(items))
+}
+
+/// GET /history/export, the lookups as a csv download
+#[get("/history/export")]
+async fn history_export(session: Session, history: web::Data<History>) -> Result<HttpResponse> {
+    let uid = user_id(&session)?;
+    let items = store(history, move |h| h.history(&uid)).await?;
+    Ok(csv_download(csv(&items, "looked_up_at"), "history.csv"))
+}
+
+/// DELETE /history
+#[delete("/history")]
+async fn history_clear(session: Session, history: web::Data<History>) -> Result<HttpResponse> {
+    let uid = user_id(&session)?;
+    store(history, move |h| h.clear_history(&uid)).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// GET /stars, starred words as json, newest first
+#[get("/stars")]
+async fn stars_list(session: Session, history: web::Data<History>) -> Result<HttpResponse> {
+    let uid = user_id(&session)?;
+    let items = store(history, move |h| h.stars(&uid)).await?;
+    Ok(HttpResponse::Ok().json(items))
+}
+
+/// GET /stars/export, starred words as a csv download
+#[get("/stars/export")]
+async fn stars_export(session: Session, history: web::Data<History>) -> Result<HttpResponse> {
+    let uid = user_id(&session)?;
+    let items = store(history, move |h| h.stars(&uid)).await?;
+    Ok(csv_download(csv(&items, "starred_at"), "stars.csv"))
+}
+
+/// PUT /stars/{word}
+#[put("/stars/{word}")]
+async fn star(session: Session, history: web::Data<History>, path: web::Path<String>) -> Result<HttpResponse> {
+    let uid = user_id(&session)?;
+    let word = path.into_inner();
+    store(history, move |h| h.star(&uid, &word)).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// DELETE /stars/{word}
+#[delete("/stars/{word}")]
+async fn unstar(session: Session, history: web::Data<History>, path: web::Path<String>) -> Result<HttpResponse> {
+    let uid = user_id(&session)?;
+    let word = path.into_inner();
+    store(history, move |h| h.unstar(&uid, &word)).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// DELETE /stars
+#[delete("/stars")]
+async fn stars_clear(session: Session, history: web::Data<History>) -> Result<HttpResponse> {
+    let uid = user_id(&session)?;
+    store(history, move |h| h.clear_stars(&uid)).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// history and favourites routes, `App::configure(history::routes)`
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(history_list)
+        .service(history_export)
+        .service(history_clear)
+        .service(stars_list)
+        .service(stars_export)
+        .service(star)
+        .service(unstar)
+        .service(stars_clear);
+}